@@ -0,0 +1,27 @@
+//! Envs:
+//! "MF_INVOICE_ACCESS_TOKEN" -- access token
+//!
+//! `cargo run --features async --example async_office`
+extern crate moneyforward_invoice_api as mf;
+extern crate env_logger;
+extern crate futures;
+extern crate tokio_core;
+
+use futures::Future;
+use mf::AsyncClient;
+use std::env;
+use tokio_core::reactor::Core;
+
+fn main() {
+    env_logger::init().unwrap();
+
+    let token = env::var("MF_INVOICE_ACCESS_TOKEN").unwrap();
+    let mut core = Core::new().unwrap();
+    let client = AsyncClient::new(token, &core.handle()).unwrap();
+
+    // 事業所情報と取引先一覧を並行して取得
+    let work = client.get_office().join(client.list_partners(1, 100));
+    let (office, partners) = core.run(work).unwrap();
+    println!("office: {:#?}", office);
+    println!("partners: {:#?}", partners.meta);
+}
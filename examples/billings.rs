@@ -5,7 +5,8 @@ extern crate env_logger;
 extern crate native_tls;
 extern crate chrono;
 
-use mf::{Client, NewBilling, NewBillingItem, NewPartner, UpdateBilling, UpdateBillingItem};
+use mf::{BillingSearch, Client, NewBilling, NewPartner, Patch, UpdateBilling, UpdateBillingItem,
+         Yen};
 use std::env;
 use chrono::NaiveDate;
 
@@ -16,21 +17,18 @@ fn main() {
     let mut client = Client::new(token).unwrap();
 
     let partner = client
-        .create_partner(NewPartner {
-            name: "サンプル取引先".into(),
-            ..Default::default()
-        })
-        .unwrap()
+        .create_partner(NewPartner::builder("サンプル取引先").build())
         .unwrap();
 
     let billing = client
-        .create_billing(NewBilling {
-            department_id: partner.departments[0].clone().id,
-            billing_date: Some(NaiveDate::from_ymd(2017, 9, 30)),
-            items: vec![NewBillingItem { ..Default::default() }],
-            ..Default::default()
-        })
-        .unwrap()
+        .create_billing(
+            NewBilling::builder(partner.departments[0].clone().id)
+                .billing_date(NaiveDate::from_ymd(2017, 9, 30))
+                .item("商品A", 1, Yen(1000))
+                .due_in_days(30)
+                .build()
+                .unwrap(),
+        )
         .unwrap();
 
     println!("created billing: {:#?}", billing);
@@ -39,7 +37,7 @@ fn main() {
         .update_billing(
             &billing.id,
             UpdateBilling {
-                memo: Some("更新しました".into()),
+                memo: Patch::Set("更新しました".into()),
                 items: vec![
                     UpdateBillingItem {
                         id: Some(billing.items[0].clone().id),
@@ -47,33 +45,31 @@ fn main() {
                         ..Default::default()
                     },
                     UpdateBillingItem {
-                        name: Some("新しい品目".into()),
+                        name: Patch::Set("新しい品目".into()),
                         ..Default::default()
                     },
                 ],
                 ..Default::default()
             },
         )
-        .unwrap()
         .unwrap();
 
     println!("updated billing: {:#?}", billing);
 
-    let billing = client.get_billing(&billing.id).unwrap().unwrap();
+    let billing = client.get_billing(&billing.id).unwrap();
     println!("got billing: {:#?}", billing);
 
 
-    let billings = client.list_billings(1, 100).unwrap().unwrap();
+    let billings = client.list_billings(1, 100).unwrap();
     println!("list metadata: {:#?}", billings.meta);
     println!("list :{:#?}", billings.billings);
 
     let billings = client
-        .search_billings(1, 100, "サンプル", None, None, None)
-        .unwrap()
+        .search_billings(1, 100, &BillingSearch::new().query("サンプル"))
         .unwrap();
     println!("search metadata: {:#?}", billings.meta);
     println!("search result :{:#?}", billings.billings);
 
-    client.delete_billing(&billing.id).unwrap().unwrap();
+    client.delete_billing(&billing.id).unwrap();
     println!("deleted the billing");
 }
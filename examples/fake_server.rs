@@ -0,0 +1,139 @@
+//! 偽のAPIサーバに対して一通りのAPIを呼ぶ。トークンもネットワークも不要
+//!
+//! 結果の検証は`fake`モジュールのテストで行っています。
+//!
+//! $ cargo run --features fake --example fake_server
+extern crate moneyforward_invoice_api as mf;
+extern crate env_logger;
+extern crate chrono;
+
+use std::io::Read;
+
+use mf::{BillingSearch, DuplicateOptions, NewBilling, NewBillingItem, NewItem, NewPartner, Patch,
+         Period, TaxCategory, UpdateBilling, UpdateBillingItem, UpdateItem, UpdateOffice,
+         UpdatePartner, Yen};
+use mf::fake::FakeServer;
+use chrono::NaiveDate;
+
+fn main() {
+    env_logger::init().unwrap();
+
+    let server = FakeServer::new();
+    let mut client = server.client().unwrap();
+
+    client
+        .update_office(UpdateOffice {
+            zip: Patch::Set("123-4567".into()),
+            registration_number: Patch::Set("T7000012050002".parse().unwrap()),
+            ..Default::default()
+        })
+        .unwrap();
+    println!("office: {:?}", client.get_office().unwrap());
+
+    let partner = client
+        .create_partner(NewPartner::builder("サンプル取引先").build())
+        .unwrap();
+    let partner = client
+        .update_partner(
+            &partner.id,
+            UpdatePartner {
+                memo: Patch::Set("更新しました".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    println!("partner: {}", client.get_partner(&partner.id).unwrap().name);
+
+    let billing = client
+        .create_billing(
+            NewBilling::builder(partner.departments[0].id.clone())
+                .billing_date(NaiveDate::from_ymd(2017, 9, 30))
+                .push_item(
+                    NewBillingItem::builder("商品A", Yen(1000))
+                        .quantity("1.5")
+                        .excise(TaxCategory::Reduced)
+                        .build(),
+                )
+                .due_in_days(30)
+                .build()
+                .unwrap(),
+        )
+        .unwrap();
+    println!("created: {} due {}", billing.total_price, billing.due_date);
+
+    let billing = client
+        .update_billing(
+            &billing.id,
+            UpdateBilling {
+                title: Patch::Set("サンプル件名".into()),
+                items: vec![
+                    UpdateBillingItem {
+                        id: Some(billing.items[0].id.clone()),
+                        _destroy: true,
+                        ..Default::default()
+                    },
+                    UpdateBillingItem {
+                        name: Patch::Set("新しい品目".into()),
+                        unit_price: Patch::Set(Yen(500)),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    println!("updated: {}", billing.total_price);
+
+    let found = client
+        .search_billings(1, 100, &BillingSearch::new().query("サンプル"))
+        .unwrap();
+    println!("found: {} billing(s)", found.billings.len());
+
+    let mut pdf = Vec::new();
+    client
+        .get_billing_pdf(&billing.id)
+        .unwrap()
+        .read_to_end(&mut pdf)
+        .unwrap();
+    println!("pdf: {} bytes", pdf.len());
+
+    client.posting_billing(&billing.id).unwrap();
+    client.cancel_posting_billing(&billing.id).unwrap();
+    println!("sent histories: {}", client.sent_histories().iter().count());
+
+    let item = client
+        .create_item(NewItem {
+            name: "商品B".into(),
+            unit_price: Some(Yen(300)),
+            ..Default::default()
+        })
+        .unwrap();
+    let item = client
+        .update_item(
+            &item.id,
+            UpdateItem {
+                quantity: Patch::Set(3),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+    println!("item: {} {}", item.name, item.price);
+    client.delete_item(&item.id).unwrap();
+
+    let copy = client
+        .duplicate_billing(
+            &billing.id,
+            &DuplicateOptions::new()
+                .shift(Period::Months(1))
+                .billing_number("2017-10-001"),
+        )
+        .unwrap();
+    println!("duplicated: {} on {}", copy.billing_number, copy.billing_date);
+    client.delete_billing(&copy.id).unwrap();
+
+    client.delete_billing(&billing.id).unwrap();
+    client.delete_partner(&partner.id).unwrap();
+    println!("left: {} partner(s), {} billing(s)", server.partners().len(), server.billings().len());
+
+    println!("every endpoint worked against the fake server");
+}
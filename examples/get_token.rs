@@ -1,15 +1,17 @@
 //! Envs:
 //! "MF_INVOICE_CLIENT_ID" -- client id
 //! "MF_INVOICE_CLIENT_SECRET" -- client secret
+//! "MF_INVOICE_TOKEN_FILE" -- (optional) file to save the token in
 
 
+extern crate moneyforward_invoice_api as mf;
 extern crate env_logger;
 extern crate native_tls;
-extern crate oauth2;
 extern crate url;
 
+use mf::auth::{OAuthConfig, Scope, Token};
+use mf::token_store::{FileTokenStore, TokenStore};
 use native_tls::{Pkcs12, TlsAcceptor, TlsStream};
-use oauth2::{Token, Config};
 use url::Url;
 use std::env;
 use std::fs::File;
@@ -83,16 +85,9 @@ fn handle_client(stream: TlsStream<TcpStream>) -> String {
 fn oauth<P: AsRef<Path>>(identity_file: P, password: &str) -> Token {
     let client_id = env::var("MF_INVOICE_CLIENT_ID").unwrap();
     let client_secret = env::var("MF_INVOICE_CLIENT_SECRET").unwrap();
-    let config = Config::new(
-        client_id,
-        client_secret,
-        "https://invoice.moneyforward.com/oauth/authorize",
-        "https://invoice.moneyforward.com/oauth/token",
-    ).add_scope("write")
-        .set_state("1234")
-        .set_redirect_url(format!("https://{}/cb", SERVER));
+    let config = OAuthConfig::new(client_id, client_secret, format!("https://{}/cb", SERVER));
 
-    let authorize_url = config.authorize_url();
+    let authorize_url = config.authorize_url(&[Scope::Write], "1234").unwrap();
 
     println!(
         "Open this URL in your browser:\n{}\n",
@@ -103,15 +98,20 @@ fn oauth<P: AsRef<Path>>(identity_file: P, password: &str) -> Token {
     let code = handle_client(stream);
 
     // Exchange the code with a token.
-    let token = config.exchange_code(code);
+    let token = config.exchange_code(&code);
 
     println!("MF returned the following token:\n{:?}\n", token);
-    token.unwrap()
+    let token = token.unwrap();
+    println!("the token expires at {:?}", token.expires_at());
+    token
 }
 
 fn main() {
     env_logger::init().unwrap();
     let identity_file = env::args().nth(1).unwrap();
     let password = env::args().nth(2).unwrap();
-    oauth(identity_file, &password);
+    let token = oauth(identity_file, &password);
+    if let Ok(path) = env::var("MF_INVOICE_TOKEN_FILE") {
+        FileTokenStore::new(path).save(&token).unwrap();
+    }
 }
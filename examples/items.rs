@@ -4,7 +4,7 @@ extern crate moneyforward_invoice_api as mf;
 extern crate env_logger;
 extern crate native_tls;
 
-use mf::{Client, NewItem, UpdateItem};
+use mf::{Client, NewItem, Patch, TaxCategory, UpdateItem, Yen};
 use std::env;
 
 fn main() {
@@ -16,11 +16,10 @@ fn main() {
     let item = client
         .create_item(NewItem {
             name: "サンプル商品".into(),
-            excise: Some(true),
-            unit_price: Some(100),
+            excise: Some(TaxCategory::Standard),
+            unit_price: Some(Yen(100)),
             ..Default::default()
         })
-        .unwrap()
         .unwrap();
 
     println!("created item: {:#?}", item);
@@ -29,25 +28,24 @@ fn main() {
         .update_item(
             &item.id,
             UpdateItem {
-                name: Some("更新商品名".into()),
+                name: Patch::Set("更新商品名".into()),
                 ..Default::default()
             },
         )
-        .unwrap()
         .unwrap();
 
     println!("updated item: {:#?}", item);
 
-    let item = client.get_item(&item.id).unwrap().unwrap();
+    let item = client.get_item(&item.id).unwrap();
 
     println!("got item: {:#?}", item);
 
-    let items = client.list_items().unwrap().unwrap();
+    let items = client.list_items().unwrap();
 
     println!("list meta: {:#?}", items.meta);
 
     println!("items: {:#?}", items.items);
 
-    client.delete_item(&item.id).unwrap().unwrap();
+    client.delete_item(&item.id).unwrap();
 
 }
@@ -13,6 +13,8 @@ fn main() {
     let token = env::var("MF_INVOICE_ACCESS_TOKEN").unwrap();
     let mut client = Client::new(token).unwrap();
 
-    let history = client.sent_history(1, 100);
-    println!("{:#?}", history);
+    // 全ページを順に取得する
+    for history in client.sent_histories().iter() {
+        println!("{:#?}", history.unwrap());
+    }
 }
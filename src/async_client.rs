@@ -0,0 +1,551 @@
+//! tokio上で動く非同期版のクライアント
+//!
+//! `async` featureを有効にすると使えます。
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use futures::{future, Future, Stream};
+use futures::future::{Loop, Shared};
+use reqwest;
+use reqwest::{Method, Url};
+use reqwest::unstable::async::{Client as HttpClient, Response};
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use serde_json;
+use tokio_core::reactor::{Handle, Timeout};
+
+use auth;
+use auth::Token;
+use credentials::Credentials;
+use duplicate::DuplicateOptions;
+use error;
+use id::{BillingId, ItemId, PartnerId};
+use model::*;
+use pagination::AsyncPager;
+use retry::{Failure, RetryPolicy};
+use rate_limit::RateLimiter;
+use validate::{self, Validate};
+use {borrow_params, build_url, decode_json, is_unauthorized, page_params, BillingSearch,
+     ClientBuilder, Error, Result};
+
+/// 非同期APIの結果
+pub type ApiFuture<T> = Box<Future<Item = T, Error = Error>>;
+
+/// 進行中のトークンの更新と、それが置き換えるアクセストークン
+///
+/// 同じトークンが拒否されたリクエストは、新しく更新せずにこれを待つ。
+type Refreshing = Rc<RefCell<Option<(String, Shared<ApiFuture<()>>)>>>;
+
+#[derive(Clone)]
+pub struct AsyncClient {
+    pub(crate) client: HttpClient,
+    pub(crate) server: Url,
+    pub(crate) credentials: Credentials,
+    pub(crate) handle: Handle,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) rate_limiter: Option<RateLimiter>,
+    pub(crate) refreshing: Refreshing,
+}
+
+impl AsyncClient {
+    pub fn new<T: Into<Token>>(token: T, handle: &Handle) -> Result<Self> {
+        ClientBuilder::new().build_async(token, handle)
+    }
+
+    /// 現在のアクセストークン
+    pub fn access_token(&self) -> String {
+        self.credentials.access_token()
+    }
+}
+
+impl AsyncClient {
+    pub fn get_office(&self) -> ApiFuture<Office> {
+        self.get("/api/v1/office.json")
+    }
+
+    pub fn update_office(&self, req: UpdateOffice) -> ApiFuture<Office> {
+        if let Err(e) = req.validate() {
+            return Box::new(future::err(e));
+        }
+        self.patch_json("/api/v1/office", &req)
+    }
+
+    pub fn list_partners(&self, page: u32, per_page: u32) -> ApiFuture<Partners> {
+        if let Err(e) = validate::page(page, per_page) {
+            return Box::new(future::err(e));
+        }
+        let (page, per_page) = (page.to_string(), per_page.to_string());
+        self.get_params(
+            "/api/v1/partners.json",
+            &page_params(&page, &per_page),
+        )
+    }
+
+    pub fn get_partner(&self, id: &PartnerId) -> ApiFuture<Partner> {
+        self.get(&format!("/api/v1/partners/{}.json", id))
+    }
+
+    pub fn create_partner(&self, req: NewPartner) -> ApiFuture<Partner> {
+        #[derive(Serialize)]
+        struct Request {
+            partner: NewPartner,
+        }
+        if let Err(e) = req.validate() {
+            return Box::new(future::err(e));
+        }
+        self.post_json("/api/v1/partners", &Request { partner: req })
+    }
+
+    pub fn update_partner(&self, id: &PartnerId, req: UpdatePartner) -> ApiFuture<Partner> {
+        #[derive(Serialize)]
+        struct Request {
+            partner: UpdatePartner,
+        }
+        if let Err(e) = req.validate() {
+            return Box::new(future::err(e));
+        }
+        self.patch_json(
+            &format!("/api/v1/partners/{}", id),
+            &Request { partner: req },
+        )
+    }
+
+    pub fn delete_partner(&self, id: &PartnerId) -> ApiFuture<()> {
+        self.delete_void(&format!("/api/v1/partners/{}.json", id))
+    }
+
+    pub fn list_billings(&self, page: u32, per_page: u32) -> ApiFuture<Billings> {
+        if let Err(e) = validate::page(page, per_page) {
+            return Box::new(future::err(e));
+        }
+        let (page, per_page) = (page.to_string(), per_page.to_string());
+        self.get_params(
+            "/api/v1/billings.json",
+            &page_params(&page, &per_page),
+        )
+    }
+
+    pub fn search_billings(
+        &self,
+        page: u32,
+        per_page: u32,
+        search: &BillingSearch,
+    ) -> ApiFuture<BillingQueryResponse> {
+        if let Err(e) = validate::page(page, per_page) {
+            return Box::new(future::err(e));
+        }
+        let params = search.params(page, per_page);
+        self.get_params("/api/v1/billings/search.json", &borrow_params(&params))
+    }
+
+    pub fn get_billing(&self, id: &BillingId) -> ApiFuture<Billing> {
+        self.get(&format!("/api/v1/billings/{}.json", id))
+    }
+
+    pub fn get_billing_pdf(&self, id: &BillingId) -> ApiFuture<BillingPdf> {
+        Box::new(
+            self.request_raw::<()>(
+                Method::Get,
+                &format!("/api/v1/billings/{}.pdf", id),
+                None,
+                None,
+            ).map(BillingPdf::new),
+        )
+    }
+
+    pub fn create_billing(&self, req: NewBilling) -> ApiFuture<Billing> {
+        #[derive(Serialize)]
+        struct Request {
+            billing: NewBilling,
+        }
+        if let Err(e) = req.validate() {
+            return Box::new(future::err(e));
+        }
+        self.post_json("/api/v1/billings", &Request { billing: req })
+    }
+
+    pub fn update_billing(&self, id: &BillingId, req: UpdateBilling) -> ApiFuture<Billing> {
+        #[derive(Serialize)]
+        struct Request {
+            billing: UpdateBilling,
+        }
+        if let Err(e) = req.validate() {
+            return Box::new(future::err(e));
+        }
+        self.patch_json(
+            &format!("/api/v1/billings/{}", id),
+            &Request { billing: req },
+        )
+    }
+
+    /// 請求書を取得し、`options`の通りに変えた新しい請求書を作る
+    pub fn duplicate_billing(
+        &self,
+        id: &BillingId,
+        options: &DuplicateOptions,
+    ) -> ApiFuture<Billing> {
+        let client = self.clone();
+        let options = options.clone();
+        Box::new(
+            self.get_billing(id)
+                .and_then(move |billing| match options.apply(&billing) {
+                    Ok(new) => client.create_billing(new),
+                    Err(e) => Box::new(future::err(e)),
+                }),
+        )
+    }
+
+    pub fn posting_billing(&self, id: &BillingId) -> ApiFuture<()> {
+        self.post_void(&format!("/api/v1/billings/{}/posting", id))
+    }
+
+    pub fn cancel_posting_billing(&self, id: &BillingId) -> ApiFuture<()> {
+        self.post_void(&format!("/api/v1/billings/{}/cancel_posting", id))
+    }
+
+    pub fn delete_billing(&self, id: &BillingId) -> ApiFuture<()> {
+        self.delete_void(&format!("/api/v1/billings/{}", id))
+    }
+
+    pub fn list_items(&self) -> ApiFuture<Items> {
+        self.get("/api/v1/items.json")
+    }
+
+    pub fn get_item(&self, id: &ItemId) -> ApiFuture<Item> {
+        self.get(&format!("/api/v1/items/{}.json", id))
+    }
+
+    pub fn create_item(&self, req: NewItem) -> ApiFuture<Item> {
+        if let Err(e) = req.validate() {
+            return Box::new(future::err(e));
+        }
+        self.post_json("/api/v1/items.json", &req)
+    }
+
+    pub fn update_item(&self, id: &ItemId, req: UpdateItem) -> ApiFuture<Item> {
+        if let Err(e) = req.validate() {
+            return Box::new(future::err(e));
+        }
+        self.patch_json(&format!("/api/v1/items/{}", id), &req)
+    }
+
+    pub fn delete_item(&self, id: &ItemId) -> ApiFuture<()> {
+        self.delete_void(&format!("/api/v1/items/{}", id))
+    }
+
+    pub fn sent_history(&self, page: u32, per_page: u32) -> ApiFuture<SentHistories> {
+        if let Err(e) = validate::page(page, per_page) {
+            return Box::new(future::err(e));
+        }
+        let (page, per_page) = (page.to_string(), per_page.to_string());
+        self.get_params(
+            "/api/v1/sent_history.json",
+            &page_params(&page, &per_page),
+        )
+    }
+}
+
+/// 一覧APIを全ページ辿る
+impl AsyncClient {
+    /// 全ての取引先
+    pub fn partners(&self) -> AsyncPager<Partners> {
+        AsyncPager::new(self.clone(), |client, page, per_page| {
+            client.list_partners(page, per_page)
+        })
+    }
+
+    /// 全ての請求書
+    pub fn billings(&self) -> AsyncPager<Billings> {
+        AsyncPager::new(self.clone(), |client, page, per_page| {
+            client.list_billings(page, per_page)
+        })
+    }
+
+    /// 条件に合う全ての請求書
+    pub fn billings_search(&self, search: BillingSearch) -> AsyncPager<BillingQueryResponse> {
+        AsyncPager::new(self.clone(), move |client, page, per_page| {
+            client.search_billings(page, per_page, &search)
+        })
+    }
+
+    /// 全ての送付履歴
+    pub fn sent_histories(&self) -> AsyncPager<SentHistories> {
+        AsyncPager::new(self.clone(), |client, page, per_page| {
+            client.sent_history(page, per_page)
+        })
+    }
+}
+
+fn read_body(res: Response) -> Box<Future<Item = Vec<u8>, Error = Error>> {
+    Box::new(
+        res.into_body()
+            .fold(Vec::new(), |mut body, chunk| {
+                body.extend_from_slice(&chunk);
+                Ok::<_, reqwest::Error>(body)
+            })
+            .map_err(Error::from),
+    )
+}
+
+impl AsyncClient {
+    /// リクエストを送り、成功したらレスポンスボディを返す
+    fn request_raw<Req>(
+        &self,
+        method: Method,
+        path: &str,
+        params: Option<&[(&str, &str)]>,
+        data: Option<&Req>,
+    ) -> ApiFuture<Vec<u8>>
+    where
+        Req: Serialize,
+    {
+        let url = match build_url(&self.server, path, params) {
+            Ok(url) => url,
+            Err(e) => return Box::new(future::err(e)),
+        };
+        let body = match data.map(serde_json::to_vec) {
+            Some(Ok(body)) => Some(body),
+            Some(Err(e)) => return Box::new(future::err(Error::Encode(e))),
+            None => None,
+        };
+
+        let token = self.credentials.access_token();
+        let client = self.clone();
+        let res = self.send_with_retry(method.clone(), url.clone(), body.clone());
+        Box::new(res.or_else(move |e| {
+            if !(is_unauthorized(&e) && client.credentials.refresh_params().is_some()) {
+                return future::Either::A(future::err(e));
+            }
+            warn!("access token was rejected: {}", e);
+            // トークンを更新して1回だけ送り直す
+            let retry = client.refresh_token(token).and_then(move |_| {
+                client.send_with_retry(method, url, body)
+            });
+            future::Either::B(retry)
+        }))
+    }
+
+    fn send_with_retry(&self, method: Method, url: Url, body: Option<Vec<u8>>) -> ApiFuture<Vec<u8>> {
+        let client = self.clone();
+        Box::new(future::loop_fn(1, move |attempt| {
+            let (client, method, url) = (client.clone(), method.clone(), url.clone());
+            client
+                .send_once(method.clone(), url.clone(), body.clone())
+                .then(move |res| {
+                    let failure = match res {
+                        Ok(body) => return future::Either::A(future::ok(Loop::Break(body))),
+                        Err(failure) => failure,
+                    };
+                    match client.retry.delay(&method, attempt, &failure) {
+                        Some(delay) => {
+                            warn!(
+                                "retrying {} {} in {:?}: {}",
+                                method,
+                                url,
+                                delay,
+                                failure.error
+                            );
+                            let wait = future::result(Timeout::new(delay, &client.handle))
+                                .flatten()
+                                .map_err(Error::Io)
+                                .map(move |_| Loop::Continue(attempt + 1));
+                            future::Either::B(wait)
+                        }
+                        None => future::Either::A(future::err(failure.error)),
+                    }
+                })
+        }))
+    }
+
+    /// `stale`が拒否されたのでトークンを更新する
+    ///
+    /// 既に更新されていれば何もせず、更新中ならその完了を待つ。
+    fn refresh_token(&self, stale: String) -> ApiFuture<()> {
+        if self.credentials.access_token() != stale {
+            return Box::new(future::ok(()));
+        }
+        let in_flight = match *self.refreshing.borrow() {
+            Some((ref refreshing, ref shared)) if *refreshing == stale => Some(shared.clone()),
+            _ => None,
+        };
+        let shared = match in_flight {
+            Some(shared) => shared,
+            None => {
+                let shared = self.request_token(stale.clone()).shared();
+                *self.refreshing.borrow_mut() = Some((stale, shared.clone()));
+                shared
+            }
+        };
+        Box::new(shared.map(|_| ()).map_err(|e| shared_error(&e)))
+    }
+
+    /// トークンエンドポイントに更新を求め、終わったら進行中の更新から外す
+    fn request_token(&self, stale: String) -> ApiFuture<()> {
+        let (oauth, refresh_token) = match self.credentials.refresh_params() {
+            Some(params) => params,
+            None => return Box::new(future::ok(())),
+        };
+        let mut reqbuilder = self.client.post(&oauth.token_url);
+        reqbuilder.form(&oauth.token_form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", &refresh_token),
+        ]));
+        let credentials = self.credentials.clone();
+        let refreshing = self.refreshing.clone();
+        Box::new(
+            reqbuilder
+                .send()
+                .map_err(Error::from)
+                .and_then(|res| {
+                    let status = res.status();
+                    read_body(res).and_then(move |body| auth::token_from_response(status, &body))
+                })
+                .then(move |token| {
+                    let mut refreshing = refreshing.borrow_mut();
+                    if refreshing.as_ref().map_or(false, |&(ref key, _)| *key == stale) {
+                        *refreshing = None;
+                    }
+                    token.map(|token| credentials.update(&stale, token))
+                }),
+        )
+    }
+
+    fn send_once(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<Vec<u8>>,
+    ) -> Box<Future<Item = Vec<u8>, Error = Failure>> {
+        use reqwest::header::{Authorization, Bearer, ContentType};
+
+        debug!("request: {} {}", method, url);
+        let mut reqbuilder = self.client.request(method, url);
+        reqbuilder.header(Authorization(Bearer { token: self.credentials.access_token() }));
+        if let Some(body) = body {
+            reqbuilder.header(ContentType::json());
+            reqbuilder.body(body);
+        }
+
+        // レート制限で待ち終わるまで送らないように、pollされてから送る
+        let res = future::lazy(move || reqbuilder.send())
+            .map_err(|e| Failure::from(Error::from(e)))
+            .and_then(|res| {
+                debug!("response: {:?}", res);
+                let status = res.status();
+                let headers = res.headers().clone();
+                read_body(res).map_err(Failure::from).and_then(
+                    move |body| if status.is_success() {
+                        Ok(body)
+                    } else {
+                        let error = error::from_response(status, &body);
+                        Err(Failure::new(error, Some(&headers)))
+                    },
+                )
+            });
+        // タイムアウトはレート制限で待った後から数える
+        let client = self.clone();
+        Box::new(self.throttle().and_then(move |_| client.with_timeout(res)))
+    }
+
+    /// レート制限に引っかかっていたら送れるようになるまで待つ
+    fn throttle(&self) -> Box<Future<Item = (), Error = Failure>> {
+        let wait = match self.rate_limiter {
+            Some(ref limiter) => limiter.reserve(),
+            None => return Box::new(future::ok(())),
+        };
+        if wait == Duration::new(0, 0) {
+            return Box::new(future::ok(()));
+        }
+        debug!("rate limited: waiting {:?}", wait);
+        Box::new(
+            future::result(Timeout::new(wait, &self.handle))
+                .flatten()
+                .map_err(|e| Failure::from(Error::Io(e))),
+        )
+    }
+
+    fn with_timeout<F>(&self, f: F) -> Box<Future<Item = F::Item, Error = Failure>>
+    where
+        F: Future<Error = Failure> + 'static,
+    {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return Box::new(f),
+        };
+        let timer = match Timeout::new(timeout, &self.handle) {
+            Ok(timer) => timer,
+            Err(e) => return Box::new(future::err(Failure::from(Error::Io(e)))),
+        };
+        Box::new(f.select2(timer).then(|res| match res {
+            Ok(future::Either::A((item, _))) => Ok(item),
+            Ok(future::Either::B(_)) => Err(Failure::from(Error::Timeout)),
+            Err(future::Either::A((e, _))) => Err(e),
+            Err(future::Either::B((e, _))) => Err(Failure::from(Error::Io(e))),
+        }))
+    }
+
+    fn request<Req, Res>(
+        &self,
+        method: Method,
+        path: &str,
+        params: Option<&[(&str, &str)]>,
+        data: Option<&Req>,
+    ) -> ApiFuture<Res>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned + 'static,
+    {
+        Box::new(self.request_raw(method, path, params, data).and_then(
+            |body| decode_json(&body),
+        ))
+    }
+
+    fn get<Res>(&self, path: &str) -> ApiFuture<Res>
+    where
+        Res: DeserializeOwned + 'static,
+    {
+        self.request::<(), _>(Method::Get, path, None, None)
+    }
+
+    fn get_params<Res>(&self, path: &str, params: &[(&str, &str)]) -> ApiFuture<Res>
+    where
+        Res: DeserializeOwned + 'static,
+    {
+        self.request::<(), _>(Method::Get, path, Some(params), None)
+    }
+
+    fn post_json<Req, Res>(&self, path: &str, req: &Req) -> ApiFuture<Res>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned + 'static,
+    {
+        self.request(Method::Post, path, None, Some(req))
+    }
+
+    fn post_void(&self, path: &str) -> ApiFuture<()> {
+        Box::new(self.request_raw::<()>(Method::Post, path, None, None).map(|_| ()))
+    }
+
+    fn patch_json<Req, Res>(&self, path: &str, req: &Req) -> ApiFuture<Res>
+    where
+        Req: Serialize,
+        Res: DeserializeOwned + 'static,
+    {
+        self.request(Method::Patch, path, None, Some(req))
+    }
+
+    fn delete_void(&self, path: &str) -> ApiFuture<()> {
+        Box::new(self.request_raw::<()>(Method::Delete, path, None, None).map(|_| ()))
+    }
+}
+
+/// 共有したトークンの更新の失敗を、待っていたリクエストそれぞれのエラーにする
+fn shared_error(error: &Error) -> Error {
+    match *error {
+        Error::OAuth(ref e) => Error::OAuth(e.clone()),
+        ref e => Error::Transport(e.to_string().into()),
+    }
+}
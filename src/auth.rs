@@ -0,0 +1,261 @@
+//! OAuth2によるアクセストークンの取得と更新
+//!
+//! [公式ドキュメント](https://github.com/moneyforward/invoice-api-doc#%E8%AA%8D%E8%A8%BC%E3%81%AB%E3%81%A4%E3%81%84%E3%81%A6)の認可コードフローに従います。
+
+use std::fmt;
+use std::error::Error as StdError;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use reqwest;
+use reqwest::{Method, Url};
+use reqwest::header::{ContentType, Headers};
+use serde_json;
+use url::form_urlencoded;
+
+use {Error, Result};
+use transport::{HttpRequest, ReqwestTransport, Transport};
+
+static AUTHORIZE_URL: &str = "https://invoice.moneyforward.com/oauth/authorize";
+static TOKEN_URL: &str = "https://invoice.moneyforward.com/oauth/token";
+
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// 認可を求める権限
+pub enum Scope {
+    /// 読み込み
+    Read,
+    /// 書き込み
+    Write,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// 登録したアプリケーションの情報
+pub struct OAuthConfig {
+    /// Client ID
+    pub client_id: String,
+    /// Client Secret
+    pub client_secret: String,
+    /// 登録したコールバックURL e.g. "https://localhost:3000/cb"
+    pub redirect_uri: String,
+    /// 認可エンドポイント
+    pub authorize_url: String,
+    /// トークンエンドポイント
+    pub token_url: String,
+}
+
+impl OAuthConfig {
+    pub fn new<S, T, U>(client_id: S, client_secret: T, redirect_uri: U) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+        U: Into<String>,
+    {
+        Self {
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            redirect_uri: redirect_uri.into(),
+            authorize_url: AUTHORIZE_URL.into(),
+            token_url: TOKEN_URL.into(),
+        }
+    }
+
+    /// ユーザにブラウザで開いてもらう認可URL
+    ///
+    /// `state`はコールバックで返ってくるので、CSRF対策に使って下さい。
+    pub fn authorize_url(&self, scopes: &[Scope], state: &str) -> Result<Url> {
+        let scope = scopes
+            .iter()
+            .map(Scope::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut url = Url::parse(&self.authorize_url)?;
+        url.query_pairs_mut()
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .append_pair("response_type", "code")
+            .append_pair("scope", &scope)
+            .append_pair("state", state);
+        Ok(url)
+    }
+
+    /// コールバックで受け取った認可コードをトークンに交換する
+    ///
+    /// プロキシなどの接続設定は使いません。必要なら`ClientBuilder::exchange_code`を使って下さい。
+    pub fn exchange_code(&self, code: &str) -> Result<Token> {
+        self.exchange_code_via(&default_transport()?, code)
+    }
+
+    /// リフレッシュトークンで新しいトークンを取得する
+    pub fn refresh(&self, refresh_token: &str) -> Result<Token> {
+        self.refresh_via(&default_transport()?, refresh_token)
+    }
+
+    fn request_token_via(&self, transport: &Transport, params: &[(&str, &str)]) -> Result<Token> {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(self.token_form(params))
+            .finish();
+        let mut headers = Headers::new();
+        headers.set(ContentType::form_url_encoded());
+        let res = transport.send(HttpRequest {
+            method: Method::Post,
+            url: Url::parse(&self.token_url)?,
+            headers,
+            body: Some(body.into_bytes()),
+        })?;
+        token_from_response(res.status, &res.body)
+    }
+}
+
+impl OAuthConfig {
+    /// `transport`を通して認可コードをトークンに交換する
+    pub(crate) fn exchange_code_via(&self, transport: &Transport, code: &str) -> Result<Token> {
+        self.request_token_via(
+            transport,
+            &[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", &self.redirect_uri),
+            ],
+        )
+    }
+
+    /// `transport`を通してトークンを更新する
+    pub(crate) fn refresh_via(&self, transport: &Transport, refresh_token: &str) -> Result<Token> {
+        self.request_token_via(
+            transport,
+            &[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+            ],
+        )
+    }
+
+    /// トークンエンドポイントに送るフォーム
+    pub(crate) fn token_form<'a>(&'a self, params: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+        let mut form = vec![
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+        ];
+        form.extend_from_slice(params);
+        form
+    }
+}
+
+/// 接続設定のないreqwestの`Transport`
+fn default_transport() -> Result<ReqwestTransport> {
+    Ok(ReqwestTransport::new(reqwest::ClientBuilder::new().build()?))
+}
+
+/// トークンエンドポイントのレスポンスを読む
+pub(crate) fn token_from_response(status: reqwest::StatusCode, body: &[u8]) -> Result<Token> {
+    if !status.is_success() {
+        return Err(match serde_json::from_slice(body) {
+            Ok(error) => Error::OAuth(error),
+            Err(_) => ::error::from_response(status, body),
+        });
+    }
+    let mut token: Token = serde_json::from_slice(body).map_err(|error| {
+        Error::Decode {
+            error,
+            body: String::from_utf8_lossy(body).into_owned(),
+        }
+    })?;
+    if token.created_at.is_none() {
+        token.created_at = Some(Utc::now().timestamp());
+    }
+    Ok(token)
+}
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+/// アクセストークン
+pub struct Token {
+    /// アクセストークン
+    pub access_token: String,
+    /// e.g. "bearer"
+    pub token_type: String,
+    /// リフレッシュトークン
+    pub refresh_token: Option<String>,
+    /// 有効期間（秒） e.g. 2592000
+    pub expires_in: Option<i64>,
+    /// 認可された権限 e.g. "write"
+    pub scope: Option<String>,
+    /// 発行日時（UNIX時間）
+    pub created_at: Option<i64>,
+}
+
+impl Token {
+    /// リフレッシュトークンのない、アクセストークンだけのトークン
+    pub fn bearer<S: Into<String>>(access_token: S) -> Self {
+        Self {
+            access_token: access_token.into(),
+            token_type: "bearer".into(),
+            refresh_token: None,
+            expires_in: None,
+            scope: None,
+            created_at: None,
+        }
+    }
+
+    /// 有効期限。分からなければ`None`
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        let created_at = NaiveDateTime::from_timestamp_opt(self.created_at?, 0)?;
+        let created_at = DateTime::<Utc>::from_utc(created_at, Utc);
+        Some(created_at + Duration::seconds(self.expires_in?))
+    }
+
+    /// 有効期限が切れているか。分からなければ`false`
+    pub fn is_expired(&self) -> bool {
+        self.expires_within(Duration::zero())
+    }
+
+    /// 有効期限が`margin`以内に切れるか。分からなければ`false`
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        self.expires_at()
+            .map_or(false, |expires_at| expires_at <= Utc::now() + margin)
+    }
+}
+
+impl From<String> for Token {
+    fn from(access_token: String) -> Self {
+        Token::bearer(access_token)
+    }
+}
+
+impl<'a> From<&'a str> for Token {
+    fn from(access_token: &'a str) -> Self {
+        Token::bearer(access_token)
+    }
+}
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+/// トークンエンドポイントのエラー
+pub struct OAuthError {
+    /// e.g. "invalid_grant"
+    pub error: String,
+    /// 詳細
+    pub error_description: Option<String>,
+}
+
+impl fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.error_description {
+            Some(ref description) => write!(f, "{}: {}", self.error, description),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+impl StdError for OAuthError {
+    fn description(&self) -> &str {
+        &self.error
+    }
+}
@@ -0,0 +1,639 @@
+//! 請求書APIのコマンドラインツール
+//!
+//! Envs:
+//! "MF_INVOICE_ACCESS_TOKEN" -- access token
+//! "MF_INVOICE_TOKEN_FILE" -- `FileTokenStore`で保存したトークン。あればこちらを使う
+//! "MF_INVOICE_CLIENT_ID", "MF_INVOICE_CLIENT_SECRET" -- あればトークンファイルのトークンを更新する
+//!
+//! ```console
+//! $ mfinvoice partner list
+//! $ mfinvoice --format json billing get ABCDEFGHIJKLMNOPQRST123
+//! $ mfinvoice billing create --file billing.yaml
+//! $ mfinvoice billing duplicate ABCDEFGHIJKLMNOPQRST123 --months 1
+//! ```
+extern crate moneyforward_invoice_api as mf;
+extern crate chrono;
+#[macro_use]
+extern crate clap;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+extern crate serde_yaml;
+
+use std::env;
+use std::error::Error as StdError;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+
+use chrono::NaiveDate;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+
+use mf::*;
+use mf::auth::OAuthConfig;
+use mf::token_store::FileTokenStore;
+
+type CliResult<T> = ::std::result::Result<T, Box<StdError>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Table,
+    Json,
+}
+
+fn main() {
+    let matches = app().get_matches();
+    if let Err(e) = run(&matches) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn app() -> App<'static, 'static> {
+    let id = || Arg::with_name("ID").required(true);
+    let file = || {
+        Arg::with_name("file")
+            .short("f")
+            .long("file")
+            .takes_value(true)
+            .required(true)
+            .help("JSON or YAML file to read the request from. \"-\" reads stdin")
+    };
+    let page = || {
+        Arg::with_name("page")
+            .long("page")
+            .takes_value(true)
+            .help("Fetch only this page instead of every page")
+    };
+    let per_page = || {
+        Arg::with_name("per_page")
+            .long("per-page")
+            .takes_value(true)
+            .default_value("100")
+            .help("Number of items to fetch per request")
+    };
+
+    App::new("mfinvoice")
+        .version(crate_version!())
+        .about("MoneyForward invoice API client")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .takes_value(true)
+                .possible_values(&["table", "json"])
+                .default_value("table")
+                .global(true),
+        )
+        .subcommand(
+            SubCommand::with_name("office")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("show"))
+                .subcommand(SubCommand::with_name("update").arg(file())),
+        )
+        .subcommand(
+            SubCommand::with_name("partner")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("list").arg(page()).arg(per_page()))
+                .subcommand(SubCommand::with_name("get").arg(id()))
+                .subcommand(SubCommand::with_name("create").arg(file()))
+                .subcommand(SubCommand::with_name("update").arg(id()).arg(file()))
+                .subcommand(SubCommand::with_name("delete").arg(id())),
+        )
+        .subcommand(
+            SubCommand::with_name("billing")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("list").arg(page()).arg(per_page()))
+                .subcommand(
+                    SubCommand::with_name("search")
+                        .arg(page())
+                        .arg(per_page())
+                        .arg(
+                            Arg::with_name("query")
+                                .short("q")
+                                .long("query")
+                                .takes_value(true)
+                                .default_value(""),
+                        )
+                        .arg(
+                            Arg::with_name("range_key")
+                                .long("range-key")
+                                .takes_value(true)
+                                .possible_values(&[
+                                    "billing_date",
+                                    "due_date",
+                                    "sales_date",
+                                    "created_at",
+                                    "updated_at",
+                                ])
+                                .help("Defaults to billing_date when --from or --to is given"),
+                        )
+                        .arg(Arg::with_name("from").long("from").takes_value(true))
+                        .arg(Arg::with_name("to").long("to").takes_value(true)),
+                )
+                .subcommand(SubCommand::with_name("get").arg(id()))
+                .subcommand(SubCommand::with_name("create").arg(file()))
+                .subcommand(SubCommand::with_name("update").arg(id()).arg(file()))
+                .subcommand(
+                    SubCommand::with_name("check")
+                        .about("Reports qualified invoice requirements the billing fails")
+                        .arg(id()),
+                )
+                .subcommand(
+                    SubCommand::with_name("duplicate")
+                        .about("Creates a copy of the billing with its dates shifted")
+                        .arg(id())
+                        .arg(
+                            Arg::with_name("months")
+                                .long("months")
+                                .takes_value(true)
+                                .allow_hyphen_values(true)
+                                .conflicts_with("days"),
+                        )
+                        .arg(
+                            Arg::with_name("days")
+                                .long("days")
+                                .takes_value(true)
+                                .allow_hyphen_values(true),
+                        )
+                        .arg(
+                            Arg::with_name("billing_number")
+                                .long("billing-number")
+                                .takes_value(true)
+                                .help("Defaults to a number assigned by the server"),
+                        ),
+                )
+                .subcommand(SubCommand::with_name("post").arg(id()))
+                .subcommand(SubCommand::with_name("cancel").arg(id()))
+                .subcommand(SubCommand::with_name("delete").arg(id()))
+                .subcommand(
+                    SubCommand::with_name("pdf").arg(id()).arg(
+                        Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .help("Defaults to <ID>.pdf. \"-\" writes stdout"),
+                    ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("item")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("list"))
+                .subcommand(SubCommand::with_name("get").arg(id()))
+                .subcommand(SubCommand::with_name("create").arg(file()))
+                .subcommand(SubCommand::with_name("update").arg(id()).arg(file()))
+                .subcommand(SubCommand::with_name("delete").arg(id())),
+        )
+        .subcommand(
+            SubCommand::with_name("sent-history")
+                .arg(page())
+                .arg(per_page()),
+        )
+}
+
+fn run(matches: &ArgMatches) -> CliResult<()> {
+    let format = match matches.value_of("format") {
+        Some("json") => Format::Json,
+        _ => Format::Table,
+    };
+    let mut client = client()?;
+    match matches.subcommand() {
+        ("office", Some(m)) => office(&mut client, format, m),
+        ("partner", Some(m)) => partner(&mut client, format, m),
+        ("billing", Some(m)) => billing(&mut client, format, m),
+        ("item", Some(m)) => item(&mut client, format, m),
+        ("sent-history", Some(m)) => {
+            let (page, per_page) = page_of(m)?;
+            let histories = match page {
+                Some(page) => client.sent_history(page, per_page)?.sent_history_list,
+                None => client
+                    .sent_histories()
+                    .per_page(per_page)
+                    .iter()
+                    .collect::<Result<_>>()?,
+            };
+            print_list(format, &histories)
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    }
+}
+
+fn client() -> CliResult<Client> {
+    if let Ok(path) = env::var("MF_INVOICE_TOKEN_FILE") {
+        let mut builder = Client::builder();
+        builder.token_store(FileTokenStore::new(path));
+        if let (Ok(id), Ok(secret)) = (
+            env::var("MF_INVOICE_CLIENT_ID"),
+            env::var("MF_INVOICE_CLIENT_SECRET"),
+        ) {
+            builder.oauth(OAuthConfig::new(id, secret, ""));
+        }
+        return Ok(builder.build_from_store()?);
+    }
+    let token = env::var("MF_INVOICE_ACCESS_TOKEN")
+        .map_err(|_| "set MF_INVOICE_ACCESS_TOKEN or MF_INVOICE_TOKEN_FILE")?;
+    Ok(Client::new(token)?)
+}
+
+fn office(client: &mut Client, format: Format, matches: &ArgMatches) -> CliResult<()> {
+    let office = match matches.subcommand() {
+        ("show", _) => client.get_office()?,
+        ("update", Some(m)) => client.update_office(read_input(m)?)?,
+        _ => unreachable!("clap requires a subcommand"),
+    };
+    print_one(format, &office)
+}
+
+fn partner(client: &mut Client, format: Format, matches: &ArgMatches) -> CliResult<()> {
+    let partner = match matches.subcommand() {
+        ("list", Some(m)) => {
+            let (page, per_page) = page_of(m)?;
+            let partners = match page {
+                Some(page) => client.list_partners(page, per_page)?.partners,
+                None => client
+                    .partners()
+                    .per_page(per_page)
+                    .iter()
+                    .collect::<Result<_>>()?,
+            };
+            return print_list(format, &partners);
+        }
+        ("get", Some(m)) => client.get_partner(&id_of(m).into())?,
+        ("create", Some(m)) => client.create_partner(read_input(m)?)?,
+        ("update", Some(m)) => client.update_partner(&id_of(m).into(), read_input(m)?)?,
+        ("delete", Some(m)) => {
+            client.delete_partner(&id_of(m).into())?;
+            return done(format, "deleted", id_of(m));
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    };
+    print_one(format, &partner)
+}
+
+fn billing(client: &mut Client, format: Format, matches: &ArgMatches) -> CliResult<()> {
+    let billing = match matches.subcommand() {
+        ("list", Some(m)) => {
+            let (page, per_page) = page_of(m)?;
+            let billings = match page {
+                Some(page) => client.list_billings(page, per_page)?.billings,
+                None => client
+                    .billings()
+                    .per_page(per_page)
+                    .iter()
+                    .collect::<Result<_>>()?,
+            };
+            return print_list(format, &billings);
+        }
+        ("search", Some(m)) => {
+            let mut search = BillingSearch::new().query(m.value_of("query").unwrap_or(""));
+            let from = date_of(m, "from")?;
+            let to = date_of(m, "to")?;
+            if let Some(range_key) = m.value_of("range_key") {
+                search = search.range(range_key.parse()?, from, to)?;
+            } else if from.is_some() || to.is_some() {
+                search = search.range(RangeKey::BillingDate, from, to)?;
+            }
+            let (page, per_page) = page_of(m)?;
+            let billings = match page {
+                Some(page) => client.search_billings(page, per_page, &search)?.billings,
+                None => client
+                    .billings_search(search)
+                    .per_page(per_page)
+                    .iter()
+                    .collect::<Result<_>>()?,
+            };
+            return print_list(format, &billings);
+        }
+        ("get", Some(m)) => client.get_billing(&id_of(m).into())?,
+        ("create", Some(m)) => client.create_billing(read_input(m)?)?,
+        ("update", Some(m)) => client.update_billing(&id_of(m).into(), read_input(m)?)?,
+        ("check", Some(m)) => {
+            let violations = invoice::check(&client.get_billing(&id_of(m).into())?);
+            if violations.is_empty() {
+                return done(format, "compliant", id_of(m));
+            }
+            let messages: Vec<String> = violations.iter().map(ToString::to_string).collect();
+            return Err(messages.join("\n").into());
+        }
+        ("duplicate", Some(m)) => {
+            let mut options = DuplicateOptions::new();
+            if let Some(months) = m.value_of("months") {
+                options = options.shift(Period::Months(months.parse()?));
+            }
+            if let Some(days) = m.value_of("days") {
+                options = options.shift(Period::Days(days.parse()?));
+            }
+            if let Some(billing_number) = m.value_of("billing_number") {
+                options = options.billing_number(billing_number);
+            }
+            client.duplicate_billing(&id_of(m).into(), &options)?
+        }
+        ("post", Some(m)) => {
+            client.posting_billing(&id_of(m).into())?;
+            return done(format, "posted", id_of(m));
+        }
+        ("cancel", Some(m)) => {
+            client.cancel_posting_billing(&id_of(m).into())?;
+            return done(format, "cancelled posting", id_of(m));
+        }
+        ("delete", Some(m)) => {
+            client.delete_billing(&id_of(m).into())?;
+            return done(format, "deleted", id_of(m));
+        }
+        ("pdf", Some(m)) => {
+            let id = id_of(m);
+            let pdf = client.get_billing_pdf(&id.into())?.into_bytes();
+            let output = m.value_of("output")
+                .map(Into::into)
+                .unwrap_or_else(|| format!("{}.pdf", id));
+            if output == "-" {
+                io::stdout().write_all(&pdf)?;
+                return Ok(());
+            }
+            File::create(&output)?.write_all(&pdf)?;
+            return done(format, "saved", &output);
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    };
+    print_one(format, &billing)
+}
+
+fn item(client: &mut Client, format: Format, matches: &ArgMatches) -> CliResult<()> {
+    let item = match matches.subcommand() {
+        ("list", _) => return print_list(format, &client.list_items()?.items),
+        ("get", Some(m)) => client.get_item(&id_of(m).into())?,
+        ("create", Some(m)) => client.create_item(read_input(m)?)?,
+        ("update", Some(m)) => client.update_item(&id_of(m).into(), read_input(m)?)?,
+        ("delete", Some(m)) => {
+            client.delete_item(&id_of(m).into())?;
+            return done(format, "deleted", id_of(m));
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    };
+    print_one(format, &item)
+}
+
+fn id_of<'a>(matches: &'a ArgMatches) -> &'a str {
+    matches.value_of("ID").expect("ID is required")
+}
+
+/// `--page`と`--per-page`。`--page`がなければ全ページを`--per-page`ずつ取得する
+fn page_of(matches: &ArgMatches) -> CliResult<(Option<u32>, u32)> {
+    let page = match matches.value_of("page") {
+        Some(page) => Some(page.parse()?),
+        None => None,
+    };
+    let per_page = matches.value_of("per_page").unwrap_or("100").parse()?;
+    Ok((page, per_page))
+}
+
+fn date_of(matches: &ArgMatches, name: &str) -> CliResult<Option<NaiveDate>> {
+    match matches.value_of(name) {
+        Some(date) => Ok(Some(date.parse()?)),
+        None => Ok(None),
+    }
+}
+
+/// `--file`をJSONかYAMLとして読む。拡張子が".json"ならJSON、それ以外はYAML
+fn read_input<T: DeserializeOwned>(matches: &ArgMatches) -> CliResult<T> {
+    let path = matches.value_of("file").expect("--file is required");
+    let mut input = String::new();
+    if path == "-" {
+        io::stdin().read_to_string(&mut input)?;
+    } else {
+        File::open(path)?.read_to_string(&mut input)?;
+    }
+    if Path::new(path).extension().map_or(false, |ext| ext == "json") {
+        Ok(serde_json::from_str(&input)?)
+    } else {
+        Ok(serde_yaml::from_str(&input)?)
+    }
+}
+
+fn done(format: Format, action: &str, target: &str) -> CliResult<()> {
+    match format {
+        Format::Json => {
+            #[derive(Serialize)]
+            struct Done<'a> {
+                action: &'a str,
+                target: &'a str,
+            }
+            println!("{}", serde_json::to_string_pretty(&Done { action, target })?);
+        }
+        Format::Table => println!("{} {}", action, target),
+    }
+    Ok(())
+}
+
+fn print_one<T: Serialize + Row>(format: Format, value: &T) -> CliResult<()> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        Format::Table => {
+            let width = T::header().iter().map(|name| display_width(name)).max();
+            for (name, cell) in T::header().iter().zip(value.row()) {
+                println!("{}  {}", pad(name, width.unwrap_or(0)), cell);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_list<T: Serialize + Row>(format: Format, values: &[T]) -> CliResult<()> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(values)?),
+        Format::Table => {
+            let header: Vec<String> = T::header().iter().map(|&name| name.into()).collect();
+            let rows: Vec<Vec<String>> = values.iter().map(Row::row).collect();
+            let widths: Vec<usize> = (0..header.len())
+                .map(|i| {
+                    rows.iter()
+                        .chain(Some(&header))
+                        .map(|row| display_width(&row[i]))
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            for row in Some(&header).into_iter().chain(&rows) {
+                let cells: Vec<String> = row.iter()
+                    .zip(&widths)
+                    .map(|(cell, &width)| pad(cell, width))
+                    .collect();
+                println!("{}", cells.join("  ").trim_right());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 端末での表示幅。ASCII以外は全角として数える
+fn display_width(s: &str) -> usize {
+    s.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+fn pad(s: &str, width: usize) -> String {
+    let padding = width.saturating_sub(display_width(s));
+    format!("{}{}", s, " ".repeat(padding))
+}
+
+fn opt(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+/// 表の1行として表示できる
+trait Row {
+    fn header() -> &'static [&'static str];
+    fn row(&self) -> Vec<String>;
+}
+
+impl Row for Office {
+    fn header() -> &'static [&'static str] {
+        &[
+            "name",
+            "zip",
+            "prefecture",
+            "address1",
+            "address2",
+            "tel",
+            "fax",
+            "registration_number",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.zip.clone(),
+            self.prefecture.clone(),
+            self.address1.clone(),
+            self.address2.clone(),
+            self.tel.clone(),
+            self.fax.clone(),
+            self.registration_number
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+        ]
+    }
+}
+
+impl Row for Partner {
+    fn header() -> &'static [&'static str] {
+        &["id", "code", "name", "departments", "updated_at"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        let departments: Vec<&str> = self.departments
+            .iter()
+            .map(|department| department.id.as_str())
+            .collect();
+        vec![
+            self.id.to_string(),
+            opt(&self.code),
+            format!("{} {}", self.name, self.name_suffix),
+            departments.join(","),
+            self.updated_at.to_rfc3339(),
+        ]
+    }
+}
+
+impl Row for Billing {
+    fn header() -> &'static [&'static str] {
+        &[
+            "id",
+            "billing_number",
+            "partner",
+            "title",
+            "billing_date",
+            "due_date",
+            "total_price",
+            "posting",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            self.billing_number.clone(),
+            self.partner_name.clone(),
+            opt(&self.title),
+            self.billing_date.to_string(),
+            self.due_date.to_string(),
+            self.total_price.to_string(),
+            self.status.posting.to_string(),
+        ]
+    }
+}
+
+impl Row for Item {
+    fn header() -> &'static [&'static str] {
+        &[
+            "id",
+            "code",
+            "name",
+            "unit_price",
+            "quantity",
+            "unit",
+            "price",
+            "excise",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.id.to_string(),
+            opt(&self.code),
+            self.name.clone(),
+            self.unit_price.map(|n| n.to_string()).unwrap_or_default(),
+            self.quantity.map(|n| n.to_string()).unwrap_or_default(),
+            opt(&self.unit),
+            self.price.to_string(),
+            self.excise.to_string(),
+        ]
+    }
+}
+
+impl Row for SentHistory {
+    fn header() -> &'static [&'static str] {
+        &["sent_at", "type", "document_type", "document_id", "to"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.sent_at.clone(),
+            self.type_.clone(),
+            self.document_type.clone(),
+            self.document_id.clone(),
+            self.to.clone(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paging(args: &[&str]) -> (Option<u32>, u32) {
+        let matches = app().get_matches_from(args);
+        let (_, partner) = matches.subcommand();
+        let (_, list) = partner.unwrap().subcommand();
+        page_of(list.unwrap()).unwrap()
+    }
+
+    #[test]
+    fn per_page_applies_with_or_without_page() {
+        assert_eq!(paging(&["mfinvoice", "partner", "list"]), (None, 100));
+        assert_eq!(
+            paging(&["mfinvoice", "partner", "list", "--per-page", "20"]),
+            (None, 20)
+        );
+        assert_eq!(
+            paging(&["mfinvoice", "partner", "list", "--page", "2", "--per-page", "20"]),
+            (Some(2), 20)
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! リクエストのビルダー
+//!
+//! 必須の項目をコンストラクタの引数で受け取るので、部門IDのない請求書や品名のない品目はコンパイルできません。
+//! 請求書は品目を1つ以上加えるまで`build`できません。
+//!
+//! ```ignore
+//! let billing = NewBilling::builder(department.id.clone())
+//!     .title("サンプル件名")
+//!     .item("商品A", 2, Yen(1000))
+//!     .due_in_days(30)
+//!     .build()?;
+//! ```
+
+use std::marker::PhantomData;
+
+use chrono::{FixedOffset, NaiveDate, Utc};
+
+use duplicate::Period;
+use id::DepartmentId;
+use invoice::RegistrationNumber;
+use model::{NewBilling, NewBillingItem, NewPartner};
+use money::Yen;
+use tax::TaxCategory;
+use Result;
+
+/// `Option<String>`のフィールドを設定するメソッドを作る
+macro_rules! string_setters {
+    ($target:ident; $($(#[$attr:meta])* $field:ident,)*) => {
+        $(
+            $(#[$attr])*
+            pub fn $field<T: Into<String>>(mut self, $field: T) -> Self {
+                self.$target.$field = Some($field.into());
+                self
+            }
+        )*
+    };
+}
+
+#[derive(Debug, Clone)]
+/// 品目がまだない請求書
+pub enum NoItems {}
+
+#[derive(Debug, Clone)]
+/// 品目が1つ以上ある請求書
+pub enum HasItems {}
+
+#[derive(Debug, Clone)]
+/// `NewBilling`のビルダー。`S`は品目があるかどうか
+pub struct NewBillingBuilder<S> {
+    billing: NewBilling,
+    due_in_days: Option<i64>,
+    state: PhantomData<S>,
+}
+
+impl NewBilling {
+    /// 請求先の部門を指定してビルダーを作る
+    pub fn builder<D: Into<DepartmentId>>(department_id: D) -> NewBillingBuilder<NoItems> {
+        NewBillingBuilder {
+            billing: NewBilling {
+                department_id: department_id.into(),
+                ..Default::default()
+            },
+            due_in_days: None,
+            state: PhantomData,
+        }
+    }
+}
+
+impl<S> NewBillingBuilder<S> {
+    string_setters! { billing;
+        /// 件名
+        title,
+        /// 請求書番号
+        billing_number,
+        /// 振込先
+        payment_condition,
+        /// 備考
+        note,
+        /// メモ
+        memo,
+        /// 帳票名
+        document_name,
+    }
+
+    /// 請求日
+    pub fn billing_date(mut self, date: NaiveDate) -> Self {
+        self.billing.billing_date = Some(date);
+        self
+    }
+
+    /// お支払期限
+    pub fn due_date(mut self, date: NaiveDate) -> Self {
+        self.billing.due_date = Some(date);
+        self.due_in_days = None;
+        self
+    }
+
+    /// お支払期限を請求日の`days`日後にする。請求日がなければ今日（日本時間）から数える
+    pub fn due_in_days(mut self, days: i64) -> Self {
+        self.due_in_days = Some(days);
+        self
+    }
+
+    /// 売上計上日
+    pub fn sales_date(mut self, date: NaiveDate) -> Self {
+        self.billing.sales_date = Some(date);
+        self
+    }
+
+    /// タグ
+    pub fn tags<I, T>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let tags: Vec<String> = tags.into_iter().map(|tag| tag.as_ref().to_owned()).collect();
+        self.billing.tags = Some(tags.join(","));
+        self
+    }
+
+    /// 登録番号。省略すると事業所の登録番号
+    pub fn registration_number(mut self, number: RegistrationNumber) -> Self {
+        self.billing.registration_number = Some(number);
+        self
+    }
+
+    /// 品目を加える
+    pub fn item<N, Q>(self, name: N, quantity: Q, unit_price: Yen) -> NewBillingBuilder<HasItems>
+    where
+        N: Into<String>,
+        Q: ToString,
+    {
+        self.push_item(
+            NewBillingItem::builder(name, unit_price)
+                .quantity(quantity)
+                .build(),
+        )
+    }
+
+    /// `NewBillingItem::builder`で作った品目を加える
+    pub fn push_item(mut self, item: NewBillingItem) -> NewBillingBuilder<HasItems> {
+        self.billing.items.push(item);
+        NewBillingBuilder {
+            billing: self.billing,
+            due_in_days: self.due_in_days,
+            state: PhantomData,
+        }
+    }
+}
+
+impl NewBillingBuilder<HasItems> {
+    /// 請求書を作る。`due_in_days`の日数で表せる範囲を超えたら`Error::DateOutOfRange`
+    pub fn build(self) -> Result<NewBilling> {
+        let mut billing = self.billing;
+        if let Some(days) = self.due_in_days {
+            let from = billing.billing_date.unwrap_or_else(today);
+            billing.due_date = Some(Period::Days(days).shift(from)?);
+        }
+        Ok(billing)
+    }
+}
+
+/// 日本時間の今日
+fn today() -> NaiveDate {
+    Utc::now()
+        .with_timezone(&FixedOffset::east(9 * 3600))
+        .date()
+        .naive_local()
+}
+
+#[derive(Debug, Clone)]
+/// `NewBillingItem`のビルダー
+pub struct NewBillingItemBuilder {
+    item: NewBillingItem,
+}
+
+impl NewBillingItem {
+    /// 品名と単価を指定してビルダーを作る。数量は1、税区分は標準税率
+    pub fn builder<S: Into<String>>(name: S, unit_price: Yen) -> NewBillingItemBuilder {
+        NewBillingItemBuilder {
+            item: NewBillingItem {
+                name: Some(name.into()),
+                unit_price: Some(unit_price),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl NewBillingItemBuilder {
+    string_setters! { item;
+        /// 品目コード
+        code,
+        /// 詳細
+        detail,
+        /// 単位 e.g. "個"
+        unit,
+    }
+
+    /// 数量 e.g. `2`、`"1.5"`
+    pub fn quantity<Q: ToString>(mut self, quantity: Q) -> Self {
+        self.item.quantity = Some(quantity.to_string());
+        self
+    }
+
+    /// 税区分
+    pub fn excise(mut self, category: TaxCategory) -> Self {
+        self.item.excise = category;
+        self
+    }
+
+    pub fn build(self) -> NewBillingItem {
+        self.item
+    }
+}
+
+#[derive(Debug, Clone)]
+/// `NewPartner`のビルダー
+pub struct NewPartnerBuilder {
+    partner: NewPartner,
+}
+
+impl NewPartner {
+    /// 取引先名を指定してビルダーを作る
+    pub fn builder<S: Into<String>>(name: S) -> NewPartnerBuilder {
+        NewPartnerBuilder {
+            partner: NewPartner {
+                name: name.into(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl NewPartnerBuilder {
+    string_setters! { partner;
+        /// 顧客コード
+        code,
+        /// 名前（カナ）
+        name_kana,
+        /// 敬称 e.g. "様"
+        name_suffix,
+        /// メモ
+        memo,
+        /// 郵便番号 e.g. "123-4567"
+        zip,
+        /// 電話番号
+        tel,
+        /// 都道府県
+        prefecture,
+        /// 住所1
+        address1,
+        /// 住所2
+        address2,
+        /// 担当者氏名
+        person_name,
+        /// 担当者役職
+        person_title,
+        /// 部門名
+        department_name,
+        /// メールアドレス
+        email,
+    }
+
+    /// ccメールアドレス
+    pub fn cc_emails<I, T>(mut self, emails: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let emails: Vec<String> = emails.into_iter().map(|email| email.as_ref().to_owned()).collect();
+        self.partner.cc_emails = Some(emails.join(","));
+        self
+    }
+
+    pub fn build(self) -> NewPartner {
+        self.partner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error;
+
+    fn builder() -> NewBillingBuilder<HasItems> {
+        NewBilling::builder("DEPARTMENT")
+            .billing_date(NaiveDate::from_ymd(2017, 9, 30))
+            .item("商品A", 2, Yen(1000))
+    }
+
+    #[test]
+    fn counts_due_days_from_the_billing_date() {
+        let billing = builder().due_in_days(31).build().unwrap();
+        assert_eq!(billing.due_date, Some(NaiveDate::from_ymd(2017, 10, 31)));
+
+        // 請求日を後から設定しても請求日から数える
+        let billing = NewBilling::builder("DEPARTMENT")
+            .due_in_days(-1)
+            .item("商品A", 1, Yen(1000))
+            .billing_date(NaiveDate::from_ymd(2018, 1, 1))
+            .build()
+            .unwrap();
+        assert_eq!(billing.due_date, Some(NaiveDate::from_ymd(2017, 12, 31)));
+    }
+
+    #[test]
+    fn counts_due_days_from_today_without_a_billing_date() {
+        let before = today();
+        let billing = NewBilling::builder("DEPARTMENT")
+            .item("商品A", 1, Yen(1000))
+            .due_in_days(10)
+            .build()
+            .unwrap();
+        let due_date = billing.due_date.unwrap();
+        assert!(due_date == before + ::chrono::Duration::days(10) ||
+            due_date == today() + ::chrono::Duration::days(10));
+        assert_eq!(billing.billing_date, None);
+    }
+
+    #[test]
+    fn the_last_due_date_setting_wins() {
+        let date = NaiveDate::from_ymd(2017, 12, 15);
+        let billing = builder().due_in_days(30).due_date(date).build().unwrap();
+        assert_eq!(billing.due_date, Some(date));
+
+        let billing = builder().due_date(date).due_in_days(30).build().unwrap();
+        assert_eq!(billing.due_date, Some(NaiveDate::from_ymd(2017, 10, 30)));
+
+        let billing = builder().build().unwrap();
+        assert_eq!(billing.due_date, None);
+    }
+
+    #[test]
+    fn rejects_due_days_out_of_range() {
+        let from = NaiveDate::from_ymd(2017, 9, 30);
+        for &days in &[i64::max_value(), i64::min_value(), 1_000_000_000] {
+            match builder().due_in_days(days).build() {
+                Err(Error::DateOutOfRange(date)) => assert_eq!(date, from),
+                other => panic!("unexpected result for {}: {:?}", days, other),
+            }
+        }
+    }
+
+    #[test]
+    fn joins_lists() {
+        let billing = builder().tags(vec!["a", "b"]).build().unwrap();
+        assert_eq!(billing.tags, Some("a,b".into()));
+        let billing = builder().tags(Vec::<String>::new()).build().unwrap();
+        assert_eq!(billing.tags, Some("".into()));
+
+        let partner = NewPartner::builder("取引先")
+            .cc_emails(&["a@example.com", "b@example.com"])
+            .build();
+        assert_eq!(
+            partner.cc_emails,
+            Some("a@example.com,b@example.com".into())
+        );
+    }
+
+    #[test]
+    fn items_default_to_the_standard_rate() {
+        let billing = builder().build().unwrap();
+        assert_eq!(
+            billing.items,
+            vec![
+                NewBillingItem {
+                    name: Some("商品A".into()),
+                    quantity: Some("2".into()),
+                    unit_price: Some(Yen(1000)),
+                    excise: TaxCategory::Standard,
+                    ..Default::default()
+                },
+            ]
+        );
+
+        let item = NewBillingItem::builder("商品B", Yen(500)).build();
+        assert_eq!(item.quantity, None);
+        assert_eq!(item.excise, TaxCategory::Standard);
+        let item = NewBillingItem::builder("商品B", Yen(500))
+            .quantity("1.5")
+            .excise(TaxCategory::Reduced)
+            .unit("kg")
+            .build();
+        assert_eq!(item.quantity, Some("1.5".into()));
+        assert_eq!(item.excise, TaxCategory::Reduced);
+        assert_eq!(item.unit, Some("kg".into()));
+    }
+}
@@ -0,0 +1,626 @@
+//! HTTPのやりとりを記録して再生する
+//!
+//! 本物のAPIに対して一度`Recorder`で記録し、テストでは`Replayer`で同じレスポンスを返します。
+//! カセットファイルはJSONで、アクセストークンや個人情報は伏せて保存します。
+//!
+//! ```ignore
+//! // 記録
+//! let transport = Recorder::new(ReqwestTransport::new(reqwest::Client::new()?), "billings.json");
+//! let mut client = Client::builder().transport(transport).build(token)?;
+//!
+//! // 再生
+//! let transport = Replayer::load("billings.json")?;
+//! let mut client = Client::builder().transport(transport).build("dummy")?;
+//! ```
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use reqwest::StatusCode;
+use reqwest::header::Headers;
+use serde_json;
+use serde_json::Value;
+use url::form_urlencoded;
+
+use transport::{HttpRequest, HttpResponse, Transport};
+use {Error, Result};
+
+/// 伏せた値の代わりに書く文字列
+pub const REDACTED: &str = "[REDACTED]";
+
+/// 値を伏せるヘッダ
+static SECRET_HEADERS: &[&str] = &["Authorization", "Cookie", "Set-Cookie"];
+
+/// デフォルトで値を伏せるJSONのフィールドとフォームのパラメータ
+///
+/// "to"や"cc"は検索条件の日付などほかの意味でも使われるので、送付履歴の中だけを伏せる。
+static DEFAULT_FIELDS: &[&str] = &[
+    // トークン
+    "access_token",
+    "refresh_token",
+    "client_secret",
+    // 個人情報
+    "email",
+    "cc_emails",
+    "tel",
+    "fax",
+    "zip",
+    "address1",
+    "address2",
+    "person_name",
+    "person_title",
+    "partner_detail",
+    "office_detail",
+    "sent_history_list[].to",
+    "sent_history_list[].cc",
+];
+
+#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Serialize, Deserialize)]
+/// 記録したやりとりの一覧
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// ファイルから読む
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut body = String::new();
+        File::open(path)?.read_to_string(&mut body)?;
+        serde_json::from_str(&body).map_err(|error| Error::Decode { error, body })
+    }
+
+    /// ファイルに書く
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let body = serde_json::to_vec_pretty(self).map_err(Error::Encode)?;
+        File::create(path)?.write_all(&body)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+/// 1回のやりとり
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+/// 記録したリクエスト
+pub struct RecordedRequest {
+    /// e.g. "GET"
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Body>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+/// 記録したレスポンス
+pub struct RecordedResponse {
+    /// e.g. 200
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Body>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// リクエストやレスポンスのボディ。カセットを読みやすいようにJSONはそのまま埋め込む
+pub enum Body {
+    Json(Value),
+    /// JSON以外のテキスト。フォームなど
+    Text(String),
+    /// PDFなどのバイナリ
+    Binary(Vec<u8>),
+}
+
+impl Body {
+    fn new(body: &[u8]) -> Option<Self> {
+        if body.is_empty() {
+            return None;
+        }
+        if let Ok(value) = serde_json::from_slice(body) {
+            return Some(Body::Json(value));
+        }
+        Some(match String::from_utf8(body.to_vec()) {
+            Ok(text) => Body::Text(text),
+            Err(e) => Body::Binary(e.into_bytes()),
+        })
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        match *self {
+            Body::Json(ref value) => serde_json::to_vec(value).map_err(Error::Encode),
+            Body::Text(ref text) => Ok(text.clone().into_bytes()),
+            Body::Binary(ref bytes) => Ok(bytes.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// カセットに書く前に値を伏せる規則
+pub struct Redactor {
+    fields: BTreeSet<String>,
+}
+
+impl Default for Redactor {
+    /// トークンと、取引先や事業所の連絡先を伏せる
+    fn default() -> Self {
+        Self {
+            fields: DEFAULT_FIELDS.iter().map(|&field| field.into()).collect(),
+        }
+    }
+}
+
+impl Redactor {
+    /// ヘッダ以外は何も伏せない
+    pub fn none() -> Self {
+        Self {
+            fields: BTreeSet::new(),
+        }
+    }
+
+    /// 値を伏せるJSONのフィールドかフォームのパラメータを追加する e.g. "name"
+    ///
+    /// "sent_history_list[].to"のように`.`と`[]`で区切ったパスを与えると、JSONのその位置だけを伏せます。
+    /// パスでなければどの深さのフィールドも伏せます。
+    pub fn field<S: Into<String>>(mut self, name: S) -> Self {
+        self.fields.insert(name.into());
+        self
+    }
+
+    fn headers(&self, headers: &Headers) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|header| {
+                let name = header.name().to_string();
+                let secret = SECRET_HEADERS
+                    .iter()
+                    .any(|secret| secret.eq_ignore_ascii_case(&name));
+                let value = if secret {
+                    REDACTED.to_string()
+                } else {
+                    header.value_string()
+                };
+                (name, value)
+            })
+            .collect()
+    }
+
+    fn body(&self, body: &[u8]) -> Option<Body> {
+        Some(match Body::new(body)? {
+            Body::Json(mut value) => {
+                self.json(&mut value);
+                Body::Json(value)
+            }
+            Body::Text(text) => Body::Text(self.form(&text)),
+            binary => binary,
+        })
+    }
+
+    fn json(&self, value: &mut Value) {
+        self.json_at("", value)
+    }
+
+    /// `path`は`value`の位置 e.g. "sent_history_list[]"
+    fn json_at(&self, path: &str, value: &mut Value) {
+        match *value {
+            Value::Object(ref mut map) => for (key, value) in map.iter_mut() {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                if (self.fields.contains(key) || self.fields.contains(&path)) && !value.is_null() {
+                    *value = Value::String(REDACTED.into());
+                } else {
+                    self.json_at(&path, value);
+                }
+            },
+            Value::Array(ref mut values) => {
+                let path = format!("{}[]", path);
+                for value in values {
+                    self.json_at(&path, value);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// フォームでなければそのまま返す
+    fn form(&self, text: &str) -> String {
+        if !text.contains('=') || text.contains(char::is_whitespace) {
+            return text.into();
+        }
+        let pairs: Vec<(String, String)> = form_urlencoded::parse(text.as_bytes())
+            .into_owned()
+            .collect();
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+        for (key, value) in pairs {
+            if self.fields.contains(&key) {
+                serializer.append_pair(&key, REDACTED);
+            } else {
+                serializer.append_pair(&key, &value);
+            }
+        }
+        serializer.finish()
+    }
+
+    fn request(&self, request: &HttpRequest) -> RecordedRequest {
+        RecordedRequest {
+            method: request.method.to_string(),
+            url: request.url.to_string(),
+            headers: self.headers(&request.headers),
+            body: request.body.as_ref().and_then(|body| self.body(body)),
+        }
+    }
+
+    fn response(&self, response: &HttpResponse) -> RecordedResponse {
+        RecordedResponse {
+            status: response.status.as_u16(),
+            headers: self.headers(&response.headers),
+            body: self.body(&response.body),
+        }
+    }
+}
+
+/// 別の`Transport`を包み、やりとりをカセットファイルに記録する
+///
+/// やりとりの度にファイルを書き直すので、途中で終了しても記録は残ります。
+/// 通信自体に失敗したやりとりは記録しません。
+pub struct Recorder<T> {
+    inner: T,
+    path: PathBuf,
+    redactor: Redactor,
+    cassette: Mutex<Cassette>,
+}
+
+impl<T: Transport> Recorder<T> {
+    /// `Redactor::default()`で伏せて`path`に記録する
+    pub fn new<P: Into<PathBuf>>(inner: T, path: P) -> Self {
+        Self::with_redactor(inner, path, Redactor::default())
+    }
+
+    pub fn with_redactor<P: Into<PathBuf>>(inner: T, path: P, redactor: Redactor) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            redactor,
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    /// ここまでに記録したやりとり
+    pub fn cassette(&self) -> Cassette {
+        self.cassette
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl<T: Transport> Transport for Recorder<T> {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let recorded = self.redactor.request(&request);
+        let response = self.inner.send(request)?;
+        let mut cassette = self.cassette.lock().unwrap_or_else(|e| e.into_inner());
+        cassette.interactions.push(Interaction {
+            request: recorded,
+            response: self.redactor.response(&response),
+        });
+        cassette.save(&self.path)?;
+        Ok(response)
+    }
+}
+
+/// カセットに記録したレスポンスを順に返す
+///
+/// リクエストはメソッド・URL・ボディ（伏せた後の値）が記録と同じ順で一致しなければ
+/// `Error::UnexpectedRequest`になります。ヘッダは比べません。
+pub struct Replayer {
+    redactor: Redactor,
+    cassette: Cassette,
+    played: Mutex<usize>,
+}
+
+impl Replayer {
+    /// 記録した時と同じ規則で伏せたカセットを再生する
+    pub fn new(cassette: Cassette, redactor: Redactor) -> Self {
+        Self {
+            redactor,
+            cassette,
+            played: Mutex::new(0),
+        }
+    }
+
+    /// `Redactor::default()`で記録したカセットファイルを読む
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(Cassette::load(path)?, Redactor::default()))
+    }
+
+    /// まだ再生していないやりとりの数。テストの最後に0か確かめる
+    pub fn remaining(&self) -> usize {
+        let played = self.played.lock().unwrap_or_else(|e| e.into_inner());
+        self.cassette.interactions.len() - *played
+    }
+}
+
+impl Transport for Replayer {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let actual = self.redactor.request(&request);
+        let mut played = self.played.lock().unwrap_or_else(|e| e.into_inner());
+        let interaction = match self.cassette.interactions.get(*played) {
+            Some(interaction) => interaction,
+            None => {
+                return Err(Error::UnexpectedRequest(format!(
+                    "{} {} (the cassette has no more interactions)",
+                    actual.method,
+                    actual.url
+                )))
+            }
+        };
+        let expected = &interaction.request;
+        if (&actual.method, &actual.url, &actual.body) !=
+            (&expected.method, &expected.url, &expected.body)
+        {
+            return Err(Error::UnexpectedRequest(format!(
+                "{} {} (expected {} {})",
+                actual.method,
+                actual.url,
+                expected.method,
+                expected.url
+            )));
+        }
+        *played += 1;
+        replay(&interaction.response)
+    }
+}
+
+fn replay(recorded: &RecordedResponse) -> Result<HttpResponse> {
+    let status = StatusCode::try_from(recorded.status).map_err(|_| {
+        Error::UnexpectedRequest(format!("invalid status in the cassette: {}", recorded.status))
+    })?;
+    let mut headers = Headers::new();
+    for &(ref name, ref value) in &recorded.headers {
+        headers.append_raw(name.clone(), value.clone());
+    }
+    let body = match recorded.body {
+        Some(ref body) => body.to_bytes()?,
+        None => Vec::new(),
+    };
+    Ok(HttpResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use reqwest::{Method, Url};
+
+    use super::*;
+    use auth::OAuthConfig;
+    use Client;
+
+    /// 毎回同じボディを返す
+    struct Stub(&'static str);
+
+    impl Transport for Stub {
+        fn send(&self, _: HttpRequest) -> Result<HttpResponse> {
+            Ok(HttpResponse {
+                status: StatusCode::Ok,
+                headers: Headers::new(),
+                body: self.0.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    fn cassette_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!(
+            "moneyforward-invoice-api-{}-{}.json",
+            name,
+            process::id()
+        ))
+    }
+
+    fn get(url: &str) -> HttpRequest {
+        HttpRequest {
+            method: Method::Get,
+            url: Url::parse(url).unwrap(),
+            headers: Headers::new(),
+            body: None,
+        }
+    }
+
+    #[test]
+    fn redacts_tokens_in_headers_and_bodies() {
+        let path = cassette_path("tokens");
+        let token = r#"{"access_token":"new-access","refresh_token":"new-refresh","token_type":"bearer"}"#;
+        let recorder = Recorder::new(Stub(token), &path);
+        let config = OAuthConfig::new("client-id", "client-secret", "https://localhost/cb");
+        config.exchange_code_via(&recorder, "the-code").unwrap();
+        let mut client = Client::builder()
+            .transport(Recorder::new(Stub("{}"), &path))
+            .build("old-access")
+            .unwrap();
+        let _ = client.get_office();
+        let saved = Cassette::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let exchange = &recorder.cassette().interactions[0];
+        match exchange.request.body {
+            Some(Body::Text(ref form)) => {
+                assert!(form.contains("client_id=client-id"));
+                assert!(form.contains("client_secret=%5BREDACTED%5D"));
+                assert!(!form.contains("client-secret"));
+            }
+            ref other => panic!("unexpected body: {:?}", other),
+        }
+        match exchange.response.body {
+            Some(Body::Json(ref token)) => {
+                assert_eq!(token["access_token"], REDACTED);
+                assert_eq!(token["refresh_token"], REDACTED);
+                assert_eq!(token["token_type"], "bearer");
+            }
+            ref other => panic!("unexpected body: {:?}", other),
+        }
+
+        let headers = &saved.interactions[0].request.headers;
+        let authorization = headers
+            .iter()
+            .find(|&&(ref name, _)| name == "Authorization")
+            .expect("an Authorization header is recorded");
+        assert_eq!(authorization.1, REDACTED);
+    }
+
+    #[test]
+    fn redacts_to_and_cc_only_in_sent_history() {
+        let mut value: Value = serde_json::from_str(
+            r#"{
+                "meta": {"condition": {"from": "2017-09-01", "to": "2017-09-30"}},
+                "sent_history_list": [{"from": "", "to": "a@example.com", "cc": "b@example.com"}]
+            }"#,
+        ).unwrap();
+        Redactor::default().json(&mut value);
+        assert_eq!(value["meta"]["condition"]["to"], "2017-09-30");
+        assert_eq!(value["sent_history_list"][0]["to"], REDACTED);
+        assert_eq!(value["sent_history_list"][0]["cc"], REDACTED);
+        assert_eq!(value["sent_history_list"][0]["from"], "");
+
+        let mut value: Value = serde_json::from_str(r#"{"a": [{"memo": "x"}], "memo": null}"#).unwrap();
+        Redactor::none().field("memo").json(&mut value);
+        assert_eq!(value["a"][0]["memo"], REDACTED);
+        assert!(value["memo"].is_null());
+    }
+
+    #[test]
+    fn rejects_unexpected_requests() {
+        let path = cassette_path("unexpected");
+        let recorder = Recorder::new(Stub("{}"), &path);
+        recorder
+            .send(get("https://invoice.moneyforward.com/api/v1/office.json"))
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let replayer = Replayer::new(recorder.cassette(), Redactor::default());
+        match replayer.send(get("https://invoice.moneyforward.com/api/v1/items.json")) {
+            Err(Error::UnexpectedRequest(ref message)) => assert!(message.contains("office.json")),
+            other => panic!("unexpected result: {:?}", other.map(|res| res.status)),
+        }
+        assert_eq!(replayer.remaining(), 1);
+        replayer
+            .send(get("https://invoice.moneyforward.com/api/v1/office.json"))
+            .unwrap();
+        assert_eq!(replayer.remaining(), 0);
+        match replayer.send(get("https://invoice.moneyforward.com/api/v1/office.json")) {
+            Err(Error::UnexpectedRequest(ref message)) => {
+                assert!(message.contains("no more interactions"))
+            }
+            other => panic!("unexpected result: {:?}", other.map(|res| res.status)),
+        }
+    }
+
+    #[cfg(feature = "fake")]
+    #[test]
+    fn replays_what_was_recorded() {
+        use std::io::Read;
+
+        use chrono::NaiveDate;
+
+        use fake::FakeServer;
+        use model::{NewBilling, NewBillingItem, NewPartner, UpdateBilling};
+        use money::Yen;
+        use patch::Patch;
+        use search::{BillingSearch, RangeKey};
+
+        /// 全ての種類のエンドポイントを呼び、比べられる値を返す
+        fn session(client: &mut Client) -> Vec<String> {
+            let office = client.get_office().unwrap();
+            let partner = client
+                .create_partner(NewPartner::builder("取引先").build())
+                .unwrap();
+            let billing = client
+                .create_billing(
+                    NewBilling::builder(partner.departments[0].id.clone())
+                        .push_item(NewBillingItem::builder("商品", Yen(1000)).build())
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            let billing = client
+                .update_billing(
+                    &billing.id,
+                    UpdateBilling {
+                        title: Patch::Set("件名".into()),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
+            let search = BillingSearch::new()
+                .query("件名")
+                .range(
+                    RangeKey::BillingDate,
+                    Some(NaiveDate::from_ymd(2000, 1, 1)),
+                    Some(NaiveDate::from_ymd(2100, 1, 1)),
+                )
+                .unwrap();
+            let found = client.search_billings(1, 10, &search).unwrap();
+            let mut pdf = Vec::new();
+            client
+                .get_billing_pdf(&billing.id)
+                .unwrap()
+                .read_to_end(&mut pdf)
+                .unwrap();
+            client.posting_billing(&billing.id).unwrap();
+            let sent = client.sent_history(1, 10).unwrap();
+            client.delete_billing(&billing.id).unwrap();
+            vec![
+                office.name,
+                partner.id.to_string(),
+                billing.id.to_string(),
+                billing.total_price.to_string(),
+                format!("{:?}", found.meta.condition),
+                format!("{:?}", found.billings.iter().map(|b| &b.id).collect::<Vec<_>>()),
+                format!("{}", pdf.len()),
+                sent.sent_history_list[0].to.clone(),
+            ]
+        }
+
+        let path = cassette_path("round-trip");
+        let server = FakeServer::new();
+        let mut client = Client::builder()
+            .transport(Recorder::new(server.clone(), &path))
+            .build(server.access_token())
+            .unwrap();
+        let mut recorded = session(&mut client);
+
+        let replayer = Replayer::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let interactions = replayer.cassette.interactions.len();
+        let mut client = Client::builder()
+            .transport(replayer)
+            .build("dummy")
+            .unwrap();
+        let replayed = session(&mut client);
+
+        // 送付先は伏せて記録される
+        assert_eq!(replayed.last().unwrap(), REDACTED);
+        recorded.pop();
+        assert_eq!(&replayed[..replayed.len() - 1], &recorded[..]);
+        assert_eq!(interactions, 9);
+    }
+}
@@ -0,0 +1,273 @@
+use std::time::Duration;
+
+use reqwest;
+use reqwest::{Certificate, Proxy, Url};
+use reqwest::header::{Headers, UserAgent};
+#[cfg(feature = "async")]
+use tokio_core::reactor::Handle;
+
+use std::sync::Arc;
+
+use {Client, Error, RateLimit, Result, RetryPolicy};
+use auth::{OAuthConfig, Token};
+use credentials::{Credentials, OnTokenRefresh};
+use rate_limit::RateLimiter;
+use token_store::TokenStore;
+use transport::{ReqwestTransport, Transport};
+#[cfg(feature = "async")]
+use AsyncClient;
+
+static SERVER: &str = "https://invoice.moneyforward.com/";
+static USER_AGENT: &str = concat!("moneyforward-invoice-api-rs/", env!("CARGO_PKG_VERSION"));
+
+/// 接続先やタイムアウトなどを設定してクライアントを作る
+pub struct ClientBuilder {
+    server: String,
+    timeout: Option<Duration>,
+    proxies: Vec<Proxy>,
+    /// DER形式のルート証明書。`Certificate`は複製できないので、クライアントを作るたびに読む
+    root_certificates: Vec<Vec<u8>>,
+    headers: Headers,
+    user_agent: String,
+    retry: RetryPolicy,
+    rate_limits: Vec<RateLimit>,
+    oauth: Option<OAuthConfig>,
+    on_token_refresh: Option<OnTokenRefresh>,
+    token_store: Option<Arc<TokenStore>>,
+    transport: Option<Arc<Transport>>,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self {
+            server: SERVER.into(),
+            timeout: None,
+            proxies: Vec::new(),
+            root_certificates: Vec::new(),
+            headers: Headers::new(),
+            user_agent: USER_AGENT.into(),
+            retry: RetryPolicy::default(),
+            rate_limits: Vec::new(),
+            oauth: None,
+            on_token_refresh: None,
+            token_store: None,
+            transport: None,
+        }
+    }
+
+    /// 接続先のURL。デフォルトは"https://invoice.moneyforward.com/"
+    pub fn server<S: Into<String>>(&mut self, server: S) -> &mut Self {
+        self.server = server.into();
+        self
+    }
+
+    /// リクエスト全体（接続から読み込み完了まで）のタイムアウト
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// プロキシを追加する
+    pub fn proxy(&mut self, proxy: Proxy) -> &mut Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// 信頼するルート証明書（DER形式）を追加する。読めない証明書は`build`でエラーになる
+    pub fn add_root_certificate(&mut self, der: &[u8]) -> &mut Self {
+        self.root_certificates.push(der.into());
+        self
+    }
+
+    /// 全てのリクエストに付けるヘッダを追加する
+    pub fn default_headers(&mut self, headers: Headers) -> &mut Self {
+        self.headers.extend(headers.iter());
+        self
+    }
+
+    /// User-Agentヘッダ。デフォルトは"moneyforward-invoice-api-rs/<version>"
+    pub fn user_agent<S: Into<String>>(&mut self, user_agent: S) -> &mut Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// リトライの設定。デフォルトは`RetryPolicy::default()`
+    pub fn retry_policy(&mut self, retry: RetryPolicy) -> &mut Self {
+        self.retry = retry;
+        self
+    }
+
+    /// レート制限を追加する。複数追加すると全てを守る
+    ///
+    /// ```ignore
+    /// builder.rate_limit(RateLimit::per_second(3)).rate_limit(RateLimit::per_minute(100));
+    /// ```
+    pub fn rate_limit(&mut self, limit: RateLimit) -> &mut Self {
+        self.rate_limits.push(limit);
+        self
+    }
+
+    /// トークンの更新に使う設定
+    ///
+    /// これとリフレッシュトークンがあれば、401が返ってきた時にトークンを更新して送り直します。
+    pub fn oauth(&mut self, config: OAuthConfig) -> &mut Self {
+        self.oauth = Some(config);
+        self
+    }
+
+    /// トークンが更新された時に呼ばれるコールバック。新しいトークンの保存などに使う
+    ///
+    /// コールバックはトークンのロックを外してから呼ぶので、中で`access_token`を読んでも構いません。
+    pub fn on_token_refresh<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&Token) + Send + Sync + 'static,
+    {
+        self.on_token_refresh = Some(Arc::new(callback));
+        self
+    }
+
+    /// トークンの保存先。更新したトークンはここに保存する
+    pub fn token_store<S: TokenStore + 'static>(&mut self, store: S) -> &mut Self {
+        self.token_store = Some(Arc::new(store));
+        self
+    }
+
+    /// リクエストの送り先。デフォルトはreqwest
+    ///
+    /// これを設定すると、タイムアウト・プロキシ・ルート証明書は同期版のクライアントでは使われません。
+    pub fn transport<T: Transport + 'static>(&mut self, transport: T) -> &mut Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// `token_store`で設定した保存先からトークンを読んでクライアントを作る
+    pub fn build_from_store(&self) -> Result<Client> {
+        let token = self.load_token()?;
+        self.build(token)
+    }
+
+    /// `token_store`で設定した保存先からトークンを読んで非同期版のクライアントを作る
+    #[cfg(feature = "async")]
+    pub fn build_async_from_store(&self, handle: &Handle) -> Result<AsyncClient> {
+        let token = self.load_token()?;
+        self.build_async(token, handle)
+    }
+
+    /// `oauth`で設定したアプリケーションで、認可コードをトークンに交換する
+    ///
+    /// プロキシやルート証明書などこのビルダーの接続設定を使うので、`OAuthConfig::exchange_code`と違ってプロキシの内側からも使えます。
+    pub fn exchange_code(&self, code: &str) -> Result<Token> {
+        match self.oauth {
+            Some(ref oauth) => oauth.exchange_code_via(&*self.build_transport()?, code),
+            None => Err(Error::OAuthNotConfigured),
+        }
+    }
+
+    /// アクセストークンを与えてクライアントを作る
+    pub fn build<T: Into<Token>>(&self, token: T) -> Result<Client> {
+        Ok(Client {
+            credentials: self.credentials(token.into()),
+            transport: self.build_transport()?,
+            headers: self.headers(),
+            server: self.server_url()?,
+            retry: self.retry.clone(),
+            rate_limiter: self.rate_limiter(),
+        })
+    }
+
+    /// アクセストークンを与えて非同期版のクライアントを作る
+    #[cfg(feature = "async")]
+    pub fn build_async<T: Into<Token>>(
+        &self,
+        token: T,
+        handle: &Handle,
+    ) -> Result<AsyncClient> {
+        let mut builder = reqwest::unstable::async::ClientBuilder::new();
+        for proxy in &self.proxies {
+            builder.proxy(proxy.clone());
+        }
+        for cert in self.certificates()? {
+            builder.add_root_certificate(cert);
+        }
+        builder.default_headers(self.headers());
+
+        Ok(AsyncClient {
+            credentials: self.credentials(token.into()),
+            client: builder.build(handle)?,
+            server: self.server_url()?,
+            handle: handle.clone(),
+            timeout: self.timeout,
+            retry: self.retry.clone(),
+            rate_limiter: self.rate_limiter(),
+            refreshing: Default::default(),
+        })
+    }
+
+    fn build_transport(&self) -> Result<Arc<Transport>> {
+        match self.transport {
+            Some(ref transport) => Ok(transport.clone()),
+            None => Ok(Arc::new(self.reqwest_transport()?)),
+        }
+    }
+
+    fn reqwest_transport(&self) -> Result<ReqwestTransport> {
+        let mut builder = reqwest::ClientBuilder::new();
+        for proxy in &self.proxies {
+            builder.proxy(proxy.clone());
+        }
+        for cert in self.certificates()? {
+            builder.add_root_certificate(cert);
+        }
+        if let Some(timeout) = self.timeout {
+            builder.timeout(timeout);
+        }
+        Ok(ReqwestTransport::new(builder.build()?))
+    }
+
+    fn certificates(&self) -> Result<Vec<Certificate>> {
+        self.root_certificates
+            .iter()
+            .map(|der| Ok(Certificate::from_der(der)?))
+            .collect()
+    }
+
+    fn load_token(&self) -> Result<Token> {
+        match self.token_store {
+            Some(ref store) => store.load()?.ok_or(Error::TokenNotFound),
+            None => Err(Error::TokenNotFound),
+        }
+    }
+
+    fn credentials(&self, token: Token) -> Credentials {
+        Credentials::new(
+            token,
+            self.oauth.clone(),
+            self.on_token_refresh.clone(),
+            self.token_store.clone(),
+        )
+    }
+
+    fn rate_limiter(&self) -> Option<RateLimiter> {
+        if self.rate_limits.is_empty() {
+            None
+        } else {
+            Some(RateLimiter::new(&self.rate_limits))
+        }
+    }
+
+    fn headers(&self) -> Headers {
+        let mut headers = self.headers.clone();
+        headers.set(UserAgent::new(self.user_agent.clone()));
+        headers
+    }
+
+    fn server_url(&self) -> Result<Url> {
+        Ok(self.server.parse::<Url>()?)
+    }
+}
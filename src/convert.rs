@@ -0,0 +1,408 @@
+//! 取得したモデルから作成・更新リクエストへの変換
+//!
+//! ```ignore
+//! let original = client.get_billing(&id)?;
+//! let mut edited = original.clone();
+//! edited.memo = Some("更新しました".into());
+//! client.update_billing(&id, UpdateBilling::diff(&original, &edited))?;
+//! ```
+
+use model::*;
+use patch::Patch;
+
+/// 値が変わっていれば新しい値を設定する
+fn changed<T: PartialEq + Clone>(old: &T, new: &T) -> Patch<T> {
+    if old == new {
+        Patch::Unchanged
+    } else {
+        Patch::Set(new.clone())
+    }
+}
+
+/// 値が変わっていれば新しい値を設定し、なくなっていれば空にする
+fn changed_opt<T: PartialEq + Clone>(old: &Option<T>, new: &Option<T>) -> Patch<T> {
+    if old == new {
+        Patch::Unchanged
+    } else {
+        Patch::from(new.clone())
+    }
+}
+
+fn quantity(quantity: Option<u32>) -> Option<String> {
+    quantity.map(|quantity| quantity.to_string())
+}
+
+fn tags(tags: &[String]) -> String {
+    tags.join(",")
+}
+
+impl Billing {
+    /// 全てのフィールドを今の値に設定する更新リクエスト
+    pub fn to_update(&self) -> UpdateBilling {
+        UpdateBilling {
+            department_id: Patch::Set(self.department_id.clone()),
+            title: Patch::from(self.title.clone()),
+            billing_number: Patch::Set(self.billing_number.clone()),
+            payment_condition: Patch::from(self.payment_condition.clone()),
+            note: Patch::from(self.note.clone()),
+            billing_date: Patch::Set(self.billing_date),
+            due_date: Patch::Set(self.due_date),
+            sales_date: Patch::Set(self.sales_date),
+            memo: Patch::from(self.memo.clone()),
+            document_name: Patch::Set(self.document_name.clone()),
+            registration_number: Patch::from(self.registration_number.clone()),
+            tags: Patch::Set(tags(&self.tags)),
+            items: self.items.iter().map(BillingItem::to_update).collect(),
+        }
+    }
+
+    /// 同じ内容の請求書を作るリクエスト。請求書番号はサーバが新しく振る
+    pub fn to_new(&self) -> NewBilling {
+        NewBilling {
+            department_id: self.department_id.clone(),
+            title: self.title.clone(),
+            billing_number: None,
+            payment_condition: self.payment_condition.clone(),
+            note: self.note.clone(),
+            billing_date: Some(self.billing_date),
+            due_date: Some(self.due_date),
+            sales_date: Some(self.sales_date),
+            memo: self.memo.clone(),
+            document_name: Some(self.document_name.clone()),
+            registration_number: self.registration_number.clone(),
+            tags: if self.tags.is_empty() {
+                None
+            } else {
+                Some(tags(&self.tags))
+            },
+            items: self.items.iter().map(BillingItem::to_new).collect(),
+        }
+    }
+}
+
+impl BillingItem {
+    /// 全てのフィールドを今の値に設定する更新リクエスト
+    pub fn to_update(&self) -> UpdateBillingItem {
+        UpdateBillingItem {
+            id: Some(self.id.clone()),
+            name: Patch::from(self.name.clone()),
+            code: Patch::from(self.code.clone()),
+            detail: Patch::from(self.detail.clone()),
+            quantity: Patch::from(quantity(self.quantity)),
+            unit_price: Patch::from(self.unit_price),
+            unit: Patch::from(self.unit.clone()),
+            excise: Patch::Set(self.excise.clone()),
+            _destroy: false,
+        }
+    }
+
+    /// 同じ内容の品目を新しい請求書に加えるリクエスト
+    pub fn to_new(&self) -> NewBillingItem {
+        NewBillingItem {
+            id: None,
+            name: self.name.clone(),
+            code: self.code.clone(),
+            detail: self.detail.clone(),
+            quantity: quantity(self.quantity),
+            unit_price: self.unit_price,
+            unit: self.unit.clone(),
+            excise: self.excise.clone(),
+        }
+    }
+}
+
+impl UpdateBilling {
+    /// `original`を`edited`にするのに必要なフィールドだけを設定した更新リクエスト
+    ///
+    /// 品目はIDで対応付けます。`edited`にない品目は削除し、`original`にない品目（IDが空のもの）は追加します。
+    /// 金額や状態などサーバが決めるフィールドは無視します。
+    pub fn diff(original: &Billing, edited: &Billing) -> Self {
+        let mut items = Vec::new();
+        for item in &edited.items {
+            match original.items.iter().find(|old| old.id == item.id) {
+                Some(old) => {
+                    let update = UpdateBillingItem::diff(old, item);
+                    if update != UpdateBillingItem::default() {
+                        items.push(UpdateBillingItem {
+                            id: Some(item.id.clone()),
+                            ..update
+                        });
+                    }
+                }
+                None => items.push(UpdateBillingItem {
+                    id: None,
+                    ..item.to_update()
+                }),
+            }
+        }
+        for old in &original.items {
+            if !edited.items.iter().any(|item| item.id == old.id) {
+                items.push(UpdateBillingItem {
+                    id: Some(old.id.clone()),
+                    _destroy: true,
+                    ..Default::default()
+                });
+            }
+        }
+
+        UpdateBilling {
+            department_id: changed(&original.department_id, &edited.department_id),
+            title: changed_opt(&original.title, &edited.title),
+            billing_number: changed(&original.billing_number, &edited.billing_number),
+            payment_condition: changed_opt(&original.payment_condition, &edited.payment_condition),
+            note: changed_opt(&original.note, &edited.note),
+            billing_date: changed(&original.billing_date, &edited.billing_date),
+            due_date: changed(&original.due_date, &edited.due_date),
+            sales_date: changed(&original.sales_date, &edited.sales_date),
+            memo: changed_opt(&original.memo, &edited.memo),
+            document_name: changed(&original.document_name, &edited.document_name),
+            registration_number: changed_opt(
+                &original.registration_number,
+                &edited.registration_number,
+            ),
+            tags: changed(&tags(&original.tags), &tags(&edited.tags)),
+            items,
+        }
+    }
+}
+
+impl UpdateBillingItem {
+    /// `original`を`edited`にするのに必要なフィールドだけを設定した更新リクエスト。IDは設定しない
+    pub fn diff(original: &BillingItem, edited: &BillingItem) -> Self {
+        UpdateBillingItem {
+            id: None,
+            name: changed_opt(&original.name, &edited.name),
+            code: changed_opt(&original.code, &edited.code),
+            detail: changed_opt(&original.detail, &edited.detail),
+            quantity: changed_opt(&quantity(original.quantity), &quantity(edited.quantity)),
+            unit_price: changed_opt(&original.unit_price, &edited.unit_price),
+            unit: changed_opt(&original.unit, &edited.unit),
+            excise: changed(&original.excise, &edited.excise),
+            _destroy: false,
+        }
+    }
+}
+
+impl Partner {
+    /// 全てのフィールドを今の値に設定する更新リクエスト
+    pub fn to_update(&self) -> UpdatePartner {
+        UpdatePartner {
+            code: Patch::from(self.code.clone()),
+            name: Patch::Set(self.name.clone()),
+            name_kana: Patch::from(self.name_kana.clone()),
+            name_suffix: Patch::Set(self.name_suffix.clone()),
+            memo: Patch::from(self.memo.clone()),
+            departments: self.departments
+                .iter()
+                .map(Department::to_update_info)
+                .collect(),
+        }
+    }
+}
+
+impl Department {
+    /// 全てのフィールドを今の値に設定する更新リクエスト
+    pub fn to_update_info(&self) -> UpdateDepartmentInfo {
+        UpdateDepartmentInfo {
+            id: Some(self.id.clone()),
+            zip: Patch::from(self.zip.clone()),
+            tel: Patch::from(self.tel.clone()),
+            prefecture: Patch::Set(self.prefecture.clone()),
+            address1: Patch::from(self.address1.clone()),
+            address2: Patch::from(self.address2.clone()),
+            person_name: Patch::from(self.person_name.clone()),
+            person_title: Patch::from(self.person_title.clone()),
+            name: Patch::from(self.name.clone()),
+            email: Patch::from(self.email.clone()),
+            cc_emails: Patch::from(self.cc_emails.clone()),
+        }
+    }
+}
+
+impl Item {
+    /// この品目を請求書に加えるリクエスト
+    pub fn to_new_billing_item(&self) -> NewBillingItem {
+        NewBillingItem {
+            id: None,
+            name: Some(self.name.clone()),
+            code: self.code.clone(),
+            detail: self.detail.clone(),
+            quantity: quantity(self.quantity),
+            unit_price: self.unit_price,
+            unit: self.unit.clone(),
+            excise: self.excise.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, NaiveDate, TimeZone};
+
+    use super::*;
+    use id::{BillingId, BillingItemId, DepartmentId, MemberId, PartnerId};
+    use money::Yen;
+    use tax::TaxCategory;
+
+    fn item(id: &str, name: &str) -> BillingItem {
+        let now = FixedOffset::east(9 * 3600).ymd(2017, 9, 30).and_hms(12, 0, 0);
+        BillingItem {
+            id: BillingItemId::new(id),
+            code: None,
+            name: Some(name.into()),
+            detail: Some("詳細".into()),
+            quantity: Some(2),
+            unit_price: Some(Yen(1000)),
+            unit: Some("個".into()),
+            price: Some(Yen(2000)),
+            display_order: 0,
+            excise: TaxCategory::Standard,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn billing() -> Billing {
+        let date = NaiveDate::from_ymd(2017, 9, 30);
+        let now = FixedOffset::east(9 * 3600).ymd(2017, 9, 30).and_hms(12, 0, 0);
+        Billing {
+            id: BillingId::new("BILLING"),
+            partner_id: PartnerId::new("PARTNER"),
+            department_id: DepartmentId::new("DEPARTMENT"),
+            partner_name: "取引先".into(),
+            partner_name_suffix: "御中".into(),
+            partner_detail: String::new(),
+            member_id: MemberId::new("MEMBER"),
+            member_name: None,
+            office_name: "事業所".into(),
+            office_detail: String::new(),
+            registration_number: None,
+            title: Some("件名".into()),
+            excise_price: Yen(400),
+            subtotal: Yen(4000),
+            memo: Some("メモ".into()),
+            payment_condition: None,
+            total_price: Yen(4400),
+            tax_summaries: Vec::new(),
+            billing_date: date,
+            due_date: date,
+            sales_date: date,
+            created_at: now,
+            updated_at: now,
+            billing_number: "1".into(),
+            note: None,
+            document_name: "請求書".into(),
+            tags: vec!["a".into(), "b".into()],
+            status: Default::default(),
+            items: vec![item("ITEM1", "商品A"), item("ITEM2", "商品B")],
+        }
+    }
+
+    #[test]
+    fn unchanged_billing_has_an_empty_diff() {
+        let original = billing();
+        let mut edited = original.clone();
+        // サーバが決めるフィールドは無視する
+        edited.total_price = Yen(0);
+        edited.updated_at = edited.updated_at + ::chrono::Duration::days(1);
+        assert_eq!(UpdateBilling::diff(&original, &edited), UpdateBilling::default());
+    }
+
+    #[test]
+    fn distinguishes_cleared_from_unchanged_fields() {
+        let original = billing();
+        let mut edited = original.clone();
+        edited.memo = None;
+        edited.note = Some("備考".into());
+        edited.tags = vec!["a".into()];
+        let diff = UpdateBilling::diff(&original, &edited);
+        assert_eq!(
+            diff,
+            UpdateBilling {
+                memo: Patch::Clear,
+                note: Patch::Set("備考".into()),
+                tags: Patch::Set("a".into()),
+                ..Default::default()
+            }
+        );
+        assert!(diff.title.is_unchanged());
+        assert!(diff.payment_condition.is_unchanged());
+    }
+
+    #[test]
+    fn updates_items_matched_by_id() {
+        let original = billing();
+        let mut edited = original.clone();
+        edited.items[1].name = Some("商品C".into());
+        edited.items[1].detail = None;
+        edited.items[1].quantity = Some(3);
+        let diff = UpdateBilling::diff(&original, &edited);
+        assert_eq!(
+            diff.items,
+            vec![
+                UpdateBillingItem {
+                    id: Some(BillingItemId::new("ITEM2")),
+                    name: Patch::Set("商品C".into()),
+                    detail: Patch::Clear,
+                    quantity: Patch::Set("3".into()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn adds_new_items_without_id() {
+        let original = billing();
+        let mut edited = original.clone();
+        edited.items.push(BillingItem {
+            id: BillingItemId::default(),
+            ..item("", "商品C")
+        });
+        let diff = UpdateBilling::diff(&original, &edited);
+        assert_eq!(
+            diff.items,
+            vec![
+                UpdateBillingItem {
+                    id: None,
+                    name: Patch::Set("商品C".into()),
+                    code: Patch::Clear,
+                    detail: Patch::Set("詳細".into()),
+                    quantity: Patch::Set("2".into()),
+                    unit_price: Patch::Set(Yen(1000)),
+                    unit: Patch::Set("個".into()),
+                    excise: Patch::Set(TaxCategory::Standard),
+                    _destroy: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn destroys_removed_items() {
+        let original = billing();
+        let mut edited = original.clone();
+        edited.items.remove(0);
+        let diff = UpdateBilling::diff(&original, &edited);
+        assert_eq!(
+            diff.items,
+            vec![
+                UpdateBillingItem {
+                    id: Some(BillingItemId::new("ITEM1")),
+                    _destroy: true,
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn converts_into_a_new_billing() {
+        let new = billing().to_new();
+        assert_eq!(new.billing_number, None);
+        assert_eq!(new.tags, Some("a,b".into()));
+        assert_eq!(new.items.len(), 2);
+        assert_eq!(new.items[0].id, None);
+        assert_eq!(new.items[0].quantity, Some("2".into()));
+    }
+}
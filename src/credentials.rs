@@ -0,0 +1,204 @@
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+
+use auth::{OAuthConfig, Token};
+use token_store::TokenStore;
+use transport::Transport;
+use Result;
+
+/// トークンが更新された時に呼ばれるコールバック
+pub(crate) type OnTokenRefresh = Arc<Fn(&Token) + Send + Sync>;
+
+/// クライアントが使うトークン。クローンしたクライアントと共有する
+#[derive(Clone)]
+pub(crate) struct Credentials {
+    token: Arc<RwLock<Token>>,
+    /// 更新を1つずつ行うためのロック。更新中もトークンは読める
+    refreshing: Arc<Mutex<()>>,
+    oauth: Option<OAuthConfig>,
+    on_refresh: Option<OnTokenRefresh>,
+    store: Option<Arc<TokenStore>>,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // トークンそのものはログに出さない
+        f.debug_struct("Credentials")
+            .field("refreshable", &self.refresh_params().is_some())
+            .finish()
+    }
+}
+
+impl Credentials {
+    pub fn new(
+        token: Token,
+        oauth: Option<OAuthConfig>,
+        on_refresh: Option<OnTokenRefresh>,
+        store: Option<Arc<TokenStore>>,
+    ) -> Self {
+        Self {
+            token: Arc::new(RwLock::new(token)),
+            refreshing: Arc::new(Mutex::new(())),
+            oauth,
+            on_refresh,
+            store,
+        }
+    }
+
+    pub fn access_token(&self) -> String {
+        let token = self.token.read().unwrap_or_else(|e| e.into_inner());
+        token.access_token.clone()
+    }
+
+    /// トークンを更新できるなら、そのための設定とリフレッシュトークンを返す
+    pub fn refresh_params(&self) -> Option<(OAuthConfig, String)> {
+        let oauth = self.oauth.clone()?;
+        let token = self.token.read().unwrap_or_else(|e| e.into_inner());
+        let refresh_token = token.refresh_token.clone()?;
+        Some((oauth, refresh_token))
+    }
+
+    /// `stale`が拒否されたのでトークンを更新する
+    ///
+    /// 他のスレッドが既に更新していたら何もしない。保存とコールバックはロックを外してから行う。
+    pub fn refresh(&self, stale: &str, transport: &Transport) -> Result<()> {
+        let refreshing = self.refreshing.lock().unwrap_or_else(|e| e.into_inner());
+        if self.access_token() != stale {
+            return Ok(());
+        }
+        let (oauth, refresh_token) = match self.refresh_params() {
+            Some(params) => params,
+            None => return Ok(()),
+        };
+        let fresh = oauth.refresh_via(transport, &refresh_token)?;
+        let replaced = self.replace(stale, fresh);
+        drop(refreshing);
+        if let Some(token) = replaced {
+            self.notify(&token);
+        }
+        Ok(())
+    }
+
+    /// 別途取得したトークンで`stale`を置き換える
+    pub fn update(&self, stale: &str, fresh: Token) {
+        if let Some(token) = self.replace(stale, fresh) {
+            self.notify(&token);
+        }
+    }
+
+    /// まだ`stale`を使っていれば置き換え、新しいトークンを返す
+    fn replace(&self, stale: &str, mut fresh: Token) -> Option<Token> {
+        let mut token = self.token.write().unwrap_or_else(|e| e.into_inner());
+        if token.access_token != stale {
+            return None;
+        }
+        info!("access token refreshed");
+        // リフレッシュトークンが返ってこなければ今のものを使い続ける
+        if fresh.refresh_token.is_none() {
+            fresh.refresh_token = token.refresh_token.take();
+        }
+        *token = fresh;
+        Some(token.clone())
+    }
+
+    /// 更新したトークンを保存し、コールバックを呼ぶ
+    fn notify(&self, token: &Token) {
+        if let Some(ref store) = self.store {
+            // リクエスト自体は続けられるので、保存に失敗しても警告にとどめる
+            if let Err(e) = store.save(token) {
+                warn!("failed to save the refreshed token: {}", e);
+            }
+        }
+        if let Some(ref on_refresh) = self.on_refresh {
+            on_refresh(token);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+    use reqwest::header::Headers;
+
+    use super::*;
+    use transport::{HttpRequest, HttpResponse};
+
+    /// トークンエンドポイントの代わり。呼ばれた回数を数え、少し待ってから新しいトークンを返す
+    #[derive(Default)]
+    struct TokenEndpoint {
+        calls: Mutex<u32>,
+    }
+
+    impl Transport for TokenEndpoint {
+        fn send(&self, _: HttpRequest) -> Result<HttpResponse> {
+            *self.calls.lock().unwrap() += 1;
+            thread::sleep(Duration::from_millis(50));
+            Ok(HttpResponse {
+                status: StatusCode::Ok,
+                headers: Headers::new(),
+                body: br#"{"access_token":"fresh","token_type":"bearer"}"#.to_vec(),
+            })
+        }
+    }
+
+    fn stale_token() -> Token {
+        Token {
+            refresh_token: Some("refresh".into()),
+            ..Token::bearer("stale")
+        }
+    }
+
+    fn oauth() -> Option<OAuthConfig> {
+        Some(OAuthConfig::new("id", "secret", "https://localhost/cb"))
+    }
+
+    #[test]
+    fn callback_can_read_the_refreshed_token() {
+        let credentials: Arc<Mutex<Option<Credentials>>> = Arc::new(Mutex::new(None));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let on_refresh: OnTokenRefresh = {
+            let (credentials, seen) = (credentials.clone(), seen.clone());
+            Arc::new(move |token: &Token| {
+                let current = credentials.lock().unwrap().clone().unwrap();
+                seen.lock()
+                    .unwrap()
+                    .push((token.access_token.clone(), current.access_token()));
+            })
+        };
+        let created = Credentials::new(stale_token(), oauth(), Some(on_refresh), None);
+        *credentials.lock().unwrap() = Some(created.clone());
+
+        created.refresh("stale", &TokenEndpoint::default()).unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![("fresh".to_string(), "fresh".to_string())]
+        );
+        // 返ってこなかったリフレッシュトークンは引き継ぐ
+        assert_eq!(
+            created.refresh_params().map(|(_, token)| token),
+            Some("refresh".into())
+        );
+    }
+
+    #[test]
+    fn concurrent_refreshes_spend_the_refresh_token_once() {
+        let credentials = Credentials::new(stale_token(), oauth(), None, None);
+        let endpoint = Arc::new(TokenEndpoint::default());
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let (credentials, endpoint) = (credentials.clone(), endpoint.clone());
+                thread::spawn(move || credentials.refresh("stale", &*endpoint).unwrap())
+            })
+            .collect();
+        // 更新中もトークンは読める
+        assert!(!credentials.access_token().is_empty());
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(*endpoint.calls.lock().unwrap(), 1);
+        assert_eq!(credentials.access_token(), "fresh");
+    }
+}
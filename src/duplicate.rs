@@ -0,0 +1,198 @@
+//! 請求書の複製
+//!
+//! ```ignore
+//! // 先月の請求書を日付だけ1ヶ月ずらして作り直す
+//! let options = DuplicateOptions::new()
+//!     .shift(Period::Months(1))
+//!     .billing_number("2017-10-001");
+//! let billing = client.duplicate_billing(&id, &options)?;
+//! ```
+
+use std::cmp;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use model::{Billing, NewBilling};
+use {Error, Result};
+
+/// `NaiveDate`で表せる範囲（約52万年）より確実に長い日数
+const MAX_DAYS: i64 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// 日付をずらす期間。負の値なら前にずらす
+pub enum Period {
+    /// 日数
+    Days(i64),
+    /// 月数
+    ///
+    /// 移った先の月にその日がなければ月末にします。月末の日付は移った先でも月末にします（e.g. 9月30日 → 10月31日）。
+    Months(i32),
+}
+
+impl Default for Period {
+    fn default() -> Self {
+        Period::Days(0)
+    }
+}
+
+impl Period {
+    /// `date`をこの期間だけずらした日付
+    ///
+    /// ずらした先が`NaiveDate`で表せなければ`Error::DateOutOfRange`を返します。
+    pub fn shift(&self, date: NaiveDate) -> Result<NaiveDate> {
+        let shifted = match *self {
+            // `Duration::days`は大きすぎる日数でパニックするので先に除く
+            Period::Days(days) if -MAX_DAYS <= days && days <= MAX_DAYS => {
+                date.checked_add_signed(Duration::days(days))
+            }
+            Period::Days(_) => None,
+            Period::Months(months) => add_months(date, months),
+        };
+        shifted.ok_or(Error::DateOutOfRange(date))
+    }
+}
+
+fn add_months(date: NaiveDate, months: i32) -> Option<NaiveDate> {
+    let index = date.year()
+        .checked_mul(12)?
+        .checked_add(date.month0() as i32)?
+        .checked_add(months)?;
+    // 負の年でも月が0から11になるように切り下げる
+    let year = if index < 0 { (index - 11) / 12 } else { index / 12 };
+    let month = (index - year * 12) as u32 + 1;
+    let last = last_day(year, month)?;
+    let day = if Some(date.day()) == last_day(date.year(), date.month()) {
+        last
+    } else {
+        cmp::min(date.day(), last)
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// その月の日数
+fn last_day(year: i32, month: u32) -> Option<u32> {
+    let (year, month) = if month == 12 {
+        (year.checked_add(1)?, 1)
+    } else {
+        (year, month + 1)
+    };
+    Some(NaiveDate::from_ymd_opt(year, month, 1)?.pred_opt()?.day())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+/// 請求書を複製する時の変更点。既定では日付も含めてそのまま複製する
+pub struct DuplicateOptions {
+    shift: Period,
+    billing_number: Option<String>,
+}
+
+impl DuplicateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 請求日、お支払期限、売上計上日を`period`だけずらす
+    pub fn shift(mut self, period: Period) -> Self {
+        self.shift = period;
+        self
+    }
+
+    /// 請求書番号。省略するとサーバが新しく振る
+    pub fn billing_number<S: Into<String>>(mut self, billing_number: S) -> Self {
+        self.billing_number = Some(billing_number.into());
+        self
+    }
+
+    /// `billing`からこの変更点で新しい請求書を作るリクエスト
+    pub fn apply(&self, billing: &Billing) -> Result<NewBilling> {
+        let shift = |date: Option<NaiveDate>| match date {
+            Some(date) => self.shift.shift(date).map(Some),
+            None => Ok(None),
+        };
+        let new = billing.to_new();
+        Ok(NewBilling {
+            billing_number: self.billing_number.clone(),
+            billing_date: shift(new.billing_date)?,
+            due_date: shift(new.due_date)?,
+            sales_date: shift(new.sales_date)?,
+            ..new
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(year, month, day)
+    }
+
+    fn months(from: NaiveDate, months: i32) -> NaiveDate {
+        Period::Months(months).shift(from).unwrap()
+    }
+
+    #[test]
+    fn moves_to_the_end_of_a_shorter_month() {
+        assert_eq!(months(date(2023, 1, 31), 1), date(2023, 2, 28));
+        assert_eq!(months(date(2024, 1, 31), 1), date(2024, 2, 29));
+        assert_eq!(months(date(2023, 1, 29), 1), date(2023, 2, 28));
+    }
+
+    #[test]
+    fn keeps_month_ends_at_the_end() {
+        assert_eq!(months(date(2017, 9, 30), 1), date(2017, 10, 31));
+        assert_eq!(months(date(2024, 2, 29), 1), date(2024, 3, 31));
+        assert_eq!(months(date(2017, 9, 29), 1), date(2017, 10, 29));
+    }
+
+    #[test]
+    fn crosses_year_boundaries() {
+        assert_eq!(months(date(2017, 12, 15), 1), date(2018, 1, 15));
+        assert_eq!(months(date(2017, 11, 15), 14), date(2019, 1, 15));
+    }
+
+    #[test]
+    fn shifts_back_with_negative_months() {
+        assert_eq!(months(date(2018, 1, 15), -1), date(2017, 12, 15));
+        assert_eq!(months(date(2017, 3, 31), -1), date(2017, 2, 28));
+        assert_eq!(months(date(2017, 10, 31), -1), date(2017, 9, 30));
+        assert_eq!(months(date(2017, 1, 15), -13), date(2015, 12, 15));
+        assert_eq!(months(date(1, 1, 15), -1), date(0, 12, 15));
+        assert_eq!(months(date(0, 1, 31), -13), date(-2, 12, 31));
+    }
+
+    #[test]
+    fn shifts_days() {
+        assert_eq!(
+            Period::Days(1).shift(date(2017, 12, 31)).unwrap(),
+            date(2018, 1, 1)
+        );
+        assert_eq!(
+            Period::Days(-30).shift(date(2017, 3, 1)).unwrap(),
+            date(2017, 1, 30)
+        );
+        assert_eq!(
+            Period::default().shift(date(2017, 3, 1)).unwrap(),
+            date(2017, 3, 1)
+        );
+    }
+
+    #[test]
+    fn out_of_range_is_an_error() {
+        let from = date(2017, 9, 30);
+        for period in &[
+            Period::Days(i64::max_value()),
+            Period::Days(i64::min_value()),
+            Period::Days(MAX_DAYS),
+            Period::Months(i32::max_value()),
+            Period::Months(i32::min_value()),
+            Period::Months(12 * 1_000_000),
+        ] {
+            match period.shift(from) {
+                Err(Error::DateOutOfRange(date)) => assert_eq!(date, from),
+                other => panic!("unexpected result for {:?}: {:?}", period, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+use chrono::NaiveDate;
+use reqwest;
+use reqwest::{StatusCode, UrlError};
+use serde_json;
+
+use auth::OAuthError;
+use model::ApiError;
+use tax::TaxCategory;
+use validate::FieldError;
+
+#[derive(Debug)]
+/// クライアントのエラー
+pub enum Error {
+    /// 通信エラー
+    Http(reqwest::Error),
+    /// APIがエラーを返した
+    Api {
+        /// HTTPステータス
+        status: StatusCode,
+        /// レスポンスのエラー内容
+        error: ApiError,
+    },
+    /// エラーレスポンスが`ApiError`として読めなかった e.g. 502のHTMLページ
+    UnexpectedResponse {
+        /// HTTPステータス
+        status: StatusCode,
+        /// レスポンスボディ
+        body: String,
+    },
+    /// 成功レスポンスがモデルとして読めなかった
+    Decode {
+        /// JSONのエラー
+        error: serde_json::Error,
+        /// レスポンスボディ
+        body: String,
+    },
+    /// トークンの取得に失敗した
+    OAuth(OAuthError),
+    /// トークンの保存先にトークンがなかった
+    TokenNotFound,
+    /// `ClientBuilder::oauth`を設定していない
+    OAuthNotConfigured,
+    /// リクエストをJSONにできなかった
+    Encode(serde_json::Error),
+    /// レスポンスの読み込みなどでのI/Oエラー
+    Io(io::Error),
+    /// 接続先のURLが不正
+    Url(UrlError),
+    /// 非同期版クライアントでタイムアウトした
+    Timeout,
+    /// `Transport`の実装が返した通信エラー
+    Transport(Box<StdError + Send + Sync>),
+    /// 再生中のカセットにないリクエストを送った
+    UnexpectedRequest(String),
+    /// 期間の開始日が終了日より後
+    InvalidDateRange {
+        from: NaiveDate,
+        to: NaiveDate,
+    },
+    /// 日付をずらした先が表せる範囲を超えた。値はずらす前の日付
+    DateOutOfRange(NaiveDate),
+    /// 品目の数量が数値として読めない
+    InvalidQuantity(String),
+    /// 金額の計算が`i64`に収まらなかった
+    AmountOverflow,
+    /// 税率を知らない税区分の消費税を計算しようとした
+    UnknownTaxCategory(TaxCategory),
+    /// 品目に現れる税区分の内訳が請求書にない
+    MissingTaxSummaries(Vec<TaxCategory>),
+    /// 送信前の検証でリクエストに問題が見つかった
+    Invalid(Vec<FieldError>),
+}
+
+impl Error {
+    /// HTTPステータスがあれば返す
+    pub fn status(&self) -> Option<StatusCode> {
+        match *self {
+            Error::Api { status, .. } |
+            Error::UnexpectedResponse { status, .. } => Some(status),
+            _ => None,
+        }
+    }
+
+    /// 送信前の検証で見つかったフィールドのエラーがあれば返す
+    pub fn field_errors(&self) -> Option<&[FieldError]> {
+        match *self {
+            Error::Invalid(ref errors) => Some(errors),
+            _ => None,
+        }
+    }
+
+    /// APIが返したエラー内容があれば返す
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match *self {
+            Error::Api { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Http(ref e) => write!(f, "http error: {}", e),
+            Error::Api { status, ref error } => write!(f, "api error ({}): {}", status, error),
+            Error::UnexpectedResponse { status, ref body } => {
+                write!(f, "unexpected response ({}): {}", status, body)
+            }
+            Error::Decode { ref error, .. } => write!(f, "failed to decode response: {}", error),
+            Error::Encode(ref e) => write!(f, "failed to encode request: {}", e),
+            Error::OAuth(ref e) => write!(f, "oauth error: {}", e),
+            Error::TokenNotFound => write!(f, "no token found in the token store"),
+            Error::OAuthNotConfigured => write!(f, "no oauth config given to the client builder"),
+            Error::Io(ref e) => write!(f, "io error: {}", e),
+            Error::Url(ref e) => write!(f, "invalid url: {}", e),
+            Error::Timeout => write!(f, "request timed out"),
+            Error::Transport(ref e) => write!(f, "transport error: {}", e),
+            Error::UnexpectedRequest(ref request) => write!(f, "unexpected request: {}", request),
+            Error::InvalidDateRange { from, to } => {
+                write!(f, "invalid date range: {} is after {}", from, to)
+            }
+            Error::DateOutOfRange(date) => write!(f, "date out of range when shifted from {}", date),
+            Error::InvalidQuantity(ref quantity) => write!(f, "invalid quantity: {:?}", quantity),
+            Error::AmountOverflow => f.write_str("amount overflowed while calculating totals"),
+            Error::UnknownTaxCategory(ref category) => {
+                write!(f, "no tax rate for category: {}", category.as_str())
+            }
+            Error::MissingTaxSummaries(ref categories) => {
+                let categories: Vec<&str> = categories.iter().map(|c| c.as_str()).collect();
+                write!(f, "missing tax summaries: {}", categories.join(", "))
+            }
+            Error::Invalid(ref errors) => {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "invalid request: {}", errors.join(", "))
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Http(ref e) => e.description(),
+            Error::Api { .. } => "api error",
+            Error::UnexpectedResponse { .. } => "unexpected response",
+            Error::Decode { ref error, .. } => error.description(),
+            Error::Encode(ref e) => e.description(),
+            Error::OAuth(ref e) => e.description(),
+            Error::TokenNotFound => "no token found in the token store",
+            Error::OAuthNotConfigured => "no oauth config given to the client builder",
+            Error::Io(ref e) => e.description(),
+            Error::Url(ref e) => e.description(),
+            Error::Timeout => "request timed out",
+            Error::Transport(ref e) => e.description(),
+            Error::UnexpectedRequest(_) => "unexpected request",
+            Error::InvalidDateRange { .. } => "invalid date range",
+            Error::DateOutOfRange(_) => "date out of range",
+            Error::InvalidQuantity(_) => "invalid quantity",
+            Error::AmountOverflow => "amount overflow",
+            Error::UnknownTaxCategory(_) => "unknown tax category",
+            Error::MissingTaxSummaries(_) => "missing tax summaries",
+            Error::Invalid(_) => "invalid request",
+        }
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        match *self {
+            Error::Http(ref e) => Some(e),
+            Error::Api { ref error, .. } => Some(error),
+            Error::Decode { ref error, .. } => Some(error),
+            Error::Encode(ref e) => Some(e),
+            Error::OAuth(ref e) => Some(e),
+            Error::Io(ref e) => Some(e),
+            Error::Url(ref e) => Some(e),
+            Error::Transport(ref e) => Some(&**e),
+            Error::UnexpectedResponse { .. } |
+            Error::TokenNotFound |
+            Error::OAuthNotConfigured |
+            Error::Timeout |
+            Error::UnexpectedRequest(_) |
+            Error::InvalidDateRange { .. } |
+            Error::DateOutOfRange(_) |
+            Error::InvalidQuantity(_) |
+            Error::AmountOverflow |
+            Error::UnknownTaxCategory(_) |
+            Error::MissingTaxSummaries(_) |
+            Error::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<UrlError> for Error {
+    fn from(e: UrlError) -> Self {
+        Error::Url(e)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let messages: Vec<&str> = self.errors.iter().map(|e| e.message.as_str()).collect();
+        write!(f, "{}: {}", self.code, messages.join(", "))
+    }
+}
+
+impl StdError for ApiError {
+    fn description(&self) -> &str {
+        "api error"
+    }
+}
+
+/// 失敗したレスポンスをエラーにする
+pub(crate) fn from_response(status: StatusCode, body: &[u8]) -> Error {
+    match serde_json::from_slice(body) {
+        Ok(error) => Error::Api { status, error },
+        Err(_) => Error::UnexpectedResponse {
+            status,
+            body: String::from_utf8_lossy(body).into_owned(),
+        },
+    }
+}
@@ -0,0 +1,1375 @@
+//! ネットワークなしで`Client`を試すための偽のAPIサーバ
+//!
+//! `fake` featureで有効になります。
+//! 状態はメモリ上にだけあり、`FakeServer`をクローンすると状態を共有します。
+//! 請求書の品目の小数の数量は金額の計算に使いますが、`BillingItem::quantity`は整数なので小数点以下を切り捨てて返します。
+//!
+//! ```ignore
+//! let server = FakeServer::new();
+//! let mut client = server.client()?;
+//! let partner = client.create_partner(NewPartner { name: "取引先".into(), ..Default::default() })?;
+//! assert_eq!(server.partners().len(), 1);
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Utc};
+use reqwest::{Method, StatusCode};
+use reqwest::header::{Authorization, Bearer, ContentType, Headers};
+use serde::de::DeserializeOwned;
+use serde::ser::Serialize;
+use serde_json;
+
+use model::*;
+use model::Error as ErrorMessage;
+use id::{BillingItemId, DepartmentId, PartnerId};
+use patch::Patch;
+use money::Yen;
+use pagination::{DEFAULT_PER_PAGE, MAX_PER_PAGE};
+use tax::{self, TaxCalculator, TaxCategory, TaxableItem};
+use transport::{HttpRequest, HttpResponse, Transport};
+use {Client, ClientBuilder, Result};
+
+/// `FakeServer::new`で受け付けるアクセストークン
+pub const FAKE_ACCESS_TOKEN: &str = "fake-access-token";
+
+/// 取引先の敬称のデフォルト
+const DEFAULT_NAME_SUFFIX: &str = "御中";
+
+/// 成功、あるいはエラーのレスポンス
+type Reply = ::std::result::Result<HttpResponse, HttpResponse>;
+
+/// 請求書の品目の正確な数量。`tax::parse_quantity`と同じ形で持つ
+type Quantities = HashMap<BillingItemId, (i64, u32)>;
+
+#[derive(Debug, Clone)]
+/// メモリ上で動く偽のAPIサーバ
+///
+/// `Transport`を実装しているので、`ClientBuilder::transport`に渡して使います。
+pub struct FakeServer {
+    state: Arc<Mutex<State>>,
+    access_token: String,
+}
+
+#[derive(Debug)]
+struct State {
+    office: Office,
+    partners: Vec<Partner>,
+    billings: Vec<Billing>,
+    quantities: Quantities,
+    items: Vec<Item>,
+    sent_history: Vec<SentHistory>,
+    last_id: u64,
+    last_billing_number: u64,
+}
+
+impl Default for FakeServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeServer {
+    /// 空の事業所を持つサーバ。アクセストークンは`FAKE_ACCESS_TOKEN`
+    pub fn new() -> Self {
+        Self::with_access_token(FAKE_ACCESS_TOKEN)
+    }
+
+    /// 受け付けるアクセストークンを指定してサーバを作る
+    pub fn with_access_token<S: Into<String>>(access_token: S) -> Self {
+        let office = Office {
+            name: "サンプル事業所".into(),
+            ..Default::default()
+        };
+        Self {
+            state: Arc::new(Mutex::new(State {
+                office,
+                partners: Vec::new(),
+                billings: Vec::new(),
+                quantities: Quantities::new(),
+                items: Vec::new(),
+                sent_history: Vec::new(),
+                last_id: 0,
+                last_billing_number: 0,
+            })),
+            access_token: access_token.into(),
+        }
+    }
+
+    /// このサーバに繋がるクライアント
+    pub fn client(&self) -> Result<Client> {
+        ClientBuilder::new()
+            .transport(self.clone())
+            .build(self.access_token.as_str())
+    }
+
+    /// 受け付けるアクセストークン
+    pub fn access_token(&self) -> &str {
+        &self.access_token
+    }
+
+    /// 現在の事業所
+    pub fn office(&self) -> Office {
+        self.state().office.clone()
+    }
+
+    /// 現在の取引先
+    pub fn partners(&self) -> Vec<Partner> {
+        self.state().partners.clone()
+    }
+
+    /// 現在の請求書
+    pub fn billings(&self) -> Vec<Billing> {
+        self.state().billings.clone()
+    }
+
+    /// 現在の品目
+    pub fn items(&self) -> Vec<Item> {
+        self.state().items.clone()
+    }
+
+    /// 現在の送付履歴
+    pub fn sent_history(&self) -> Vec<SentHistory> {
+        self.state().sent_history.clone()
+    }
+
+    fn state(&self) -> MutexGuard<State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_authorized(&self, headers: &Headers) -> bool {
+        match headers.get::<Authorization<Bearer>>() {
+            Some(auth) => auth.0.token == self.access_token,
+            None => false,
+        }
+    }
+}
+
+impl Transport for FakeServer {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        debug!("fake server: {} {}", request.method, request.url);
+        if !self.is_authorized(&request.headers) {
+            return Ok(error(StatusCode::Unauthorized, "アクセストークンが無効です。"));
+        }
+        let mut state = self.state();
+        Ok(match state.handle(&request) {
+            Ok(res) | Err(res) => res,
+        })
+    }
+}
+
+impl State {
+    fn handle(&mut self, request: &HttpRequest) -> Reply {
+        let path = match request.url.path().trim_left_matches('/').splitn(3, '/').nth(2) {
+            Some(path) if request.url.path().starts_with("/api/v1/") => path.to_string(),
+            _ => return Err(not_found()),
+        };
+        let (path, pdf) = if path.ends_with(".pdf") {
+            (path.trim_right_matches(".pdf").to_string(), true)
+        } else {
+            (path.trim_right_matches(".json").to_string(), false)
+        };
+        let segments: Vec<&str> = path.split('/').collect();
+        if segments.len() > 3 {
+            return Err(not_found());
+        }
+        let resource = segments[0];
+        let id = segments.get(1).cloned();
+        let action = segments.get(2).cloned();
+        let query: HashMap<String, String> = request.url.query_pairs().into_owned().collect();
+        let body = &request.body;
+
+        match (&request.method, resource, id, action, pdf) {
+            (&Method::Get, "office", None, None, false) => Ok(json(StatusCode::Ok, &self.office)),
+            (&Method::Patch, "office", None, None, false) => self.update_office(decode(body)?),
+
+            (&Method::Get, "partners", None, None, false) => self.list_partners(&query),
+            (&Method::Post, "partners", None, None, false) => {
+                self.create_partner(decode::<PartnerRequest<NewPartner>>(body)?.partner)
+            }
+            (&Method::Get, "partners", Some(id), None, false) => {
+                let i = self.partner_index(id)?;
+                Ok(json(StatusCode::Ok, &self.partners[i]))
+            }
+            (&Method::Patch, "partners", Some(id), None, false) => {
+                self.update_partner(id, decode::<PartnerRequest<UpdatePartner>>(body)?.partner)
+            }
+            (&Method::Delete, "partners", Some(id), None, false) => self.delete_partner(id),
+
+            (&Method::Get, "billings", None, None, false) => self.list_billings(&query),
+            (&Method::Get, "billings", Some("search"), None, false) => self.search_billings(&query),
+            (&Method::Post, "billings", None, None, false) => {
+                self.create_billing(decode::<BillingRequest<NewBilling>>(body)?.billing)
+            }
+            (&Method::Get, "billings", Some(id), None, false) => {
+                let i = self.billing_index(id)?;
+                Ok(json(StatusCode::Ok, &self.billings[i]))
+            }
+            (&Method::Get, "billings", Some(id), None, true) => {
+                let i = self.billing_index(id)?;
+                Ok(billing_pdf(&self.billings[i]))
+            }
+            (&Method::Patch, "billings", Some(id), None, false) => {
+                self.update_billing(id, decode::<BillingRequest<UpdateBilling>>(body)?.billing)
+            }
+            (&Method::Delete, "billings", Some(id), None, false) => {
+                let i = self.billing_index(id)?;
+                self.billings.remove(i);
+                Ok(no_content())
+            }
+            (&Method::Post, "billings", Some(id), Some("posting"), false) => self.posting_billing(id),
+            (&Method::Post, "billings", Some(id), Some("cancel_posting"), false) => {
+                self.cancel_posting_billing(id)
+            }
+
+            (&Method::Get, "items", None, None, false) => {
+                let total_count = self.items.len() as u32;
+                Ok(json(
+                    StatusCode::Ok,
+                    &Items {
+                        meta: ItemsMeta {
+                            total_count,
+                            total_pages: 1,
+                            current_page: 1,
+                            per_page: total_count,
+                        },
+                        items: self.items.clone(),
+                    },
+                ))
+            }
+            (&Method::Post, "items", None, None, false) => self.create_item(decode(body)?),
+            (&Method::Get, "items", Some(id), None, false) => {
+                let i = self.item_index(id)?;
+                Ok(json(StatusCode::Ok, &self.items[i]))
+            }
+            (&Method::Patch, "items", Some(id), None, false) => self.update_item(id, decode(body)?),
+            (&Method::Delete, "items", Some(id), None, false) => {
+                let i = self.item_index(id)?;
+                self.items.remove(i);
+                Ok(no_content())
+            }
+
+            (&Method::Get, "sent_history", None, None, false) => {
+                let (sent_history_list, meta) = paginate(&self.sent_history, &query)?;
+                Ok(json(
+                    StatusCode::Ok,
+                    &SentHistories {
+                        meta,
+                        sent_history_list,
+                    },
+                ))
+            }
+
+            _ => Err(not_found()),
+        }
+    }
+
+    fn next_id<T: From<String>>(&mut self) -> T {
+        self.last_id += 1;
+        T::from(format!("FAKE{:019}", self.last_id))
+    }
+
+    fn partner_index(&self, id: &str) -> ::std::result::Result<usize, HttpResponse> {
+        self.partners
+            .iter()
+            .position(|partner| partner.id.as_str() == id)
+            .ok_or_else(not_found)
+    }
+
+    fn billing_index(&self, id: &str) -> ::std::result::Result<usize, HttpResponse> {
+        self.billings
+            .iter()
+            .position(|billing| billing.id.as_str() == id)
+            .ok_or_else(not_found)
+    }
+
+    fn item_index(&self, id: &str) -> ::std::result::Result<usize, HttpResponse> {
+        self.items
+            .iter()
+            .position(|item| item.id.as_str() == id)
+            .ok_or_else(not_found)
+    }
+
+    fn update_office(&mut self, req: UpdateOffice) -> Reply {
+        {
+            let office = &mut self.office;
+            set(&mut office.name, req.name);
+            set_str(&mut office.zip, req.zip);
+            set_str(&mut office.prefecture, req.prefecture);
+            set_str(&mut office.address1, req.address1);
+            set_str(&mut office.address2, req.address2);
+            set_str(&mut office.tel, req.tel);
+            set_str(&mut office.fax, req.fax);
+            set_opt(&mut office.registration_number, req.registration_number);
+        }
+        Ok(json(StatusCode::Ok, &self.office))
+    }
+
+    fn list_partners(&self, query: &HashMap<String, String>) -> Reply {
+        let (partners, meta) = paginate(&self.partners, query)?;
+        Ok(json(StatusCode::Ok, &Partners { meta, partners }))
+    }
+
+    fn create_partner(&mut self, req: NewPartner) -> Reply {
+        if req.name.is_empty() {
+            return Err(bad_request("名前を入力してください。"));
+        }
+        let now = now();
+        let department = Department {
+            id: self.next_id(),
+            zip: req.zip,
+            tel: req.tel,
+            prefecture: req.prefecture.unwrap_or_default(),
+            address1: req.address1,
+            address2: req.address2,
+            person_name: req.person_name,
+            person_title: req.person_title,
+            name: req.department_name,
+            email: req.email,
+            cc_emails: req.cc_emails,
+        };
+        let partner = Partner {
+            id: self.next_id(),
+            code: req.code,
+            name: req.name,
+            name_kana: req.name_kana,
+            name_suffix: req.name_suffix
+                .unwrap_or_else(|| DEFAULT_NAME_SUFFIX.into()),
+            memo: req.memo,
+            departments: vec![department],
+            created_at: now,
+            updated_at: now,
+        };
+        self.partners.push(partner.clone());
+        Ok(json(StatusCode::Created, &partner))
+    }
+
+    fn update_partner(&mut self, id: &str, req: UpdatePartner) -> Reply {
+        let i = self.partner_index(id)?;
+        let mut new_ids = Vec::new();
+        for department in &req.departments {
+            if department.id.is_none() {
+                new_ids.push(self.next_id());
+            }
+        }
+        let mut new_ids = new_ids.into_iter();
+
+        let mut partner = self.partners[i].clone();
+        set(&mut partner.name, req.name);
+        set_opt(&mut partner.code, req.code);
+        set_opt(&mut partner.name_kana, req.name_kana);
+        set(&mut partner.name_suffix, req.name_suffix);
+        set_opt(&mut partner.memo, req.memo);
+        for info in req.departments {
+            let index = match info.id {
+                Some(ref id) => partner
+                    .departments
+                    .iter()
+                    .position(|department| &department.id == id)
+                    .ok_or_else(|| bad_request("部門が見つかりません。"))?,
+                None => {
+                    partner.departments.push(Department {
+                        id: new_ids.next().expect("an id is reserved for each new department"),
+                        ..Default::default()
+                    });
+                    partner.departments.len() - 1
+                }
+            };
+            let department = &mut partner.departments[index];
+            set_opt(&mut department.zip, info.zip);
+            set_opt(&mut department.tel, info.tel);
+            set_str(&mut department.prefecture, info.prefecture);
+            set_opt(&mut department.address1, info.address1);
+            set_opt(&mut department.address2, info.address2);
+            set_opt(&mut department.person_name, info.person_name);
+            set_opt(&mut department.person_title, info.person_title);
+            set_opt(&mut department.name, info.name);
+            set_opt(&mut department.email, info.email);
+            set_opt(&mut department.cc_emails, info.cc_emails);
+        }
+        partner.updated_at = now();
+        self.partners[i] = partner.clone();
+        Ok(json(StatusCode::Ok, &partner))
+    }
+
+    fn delete_partner(&mut self, id: &str) -> Reply {
+        let i = self.partner_index(id)?;
+        if self.billings.iter().any(|billing| billing.partner_id.as_str() == id) {
+            return Err(error(
+                StatusCode::UnprocessableEntity,
+                "請求書が存在する取引先は削除できません。",
+            ));
+        }
+        self.partners.remove(i);
+        Ok(no_content())
+    }
+
+    /// 部門IDから取引先と部門を探す
+    fn find_department(
+        &self,
+        department_id: &DepartmentId,
+    ) -> ::std::result::Result<(&Partner, &Department), HttpResponse> {
+        for partner in &self.partners {
+            for department in &partner.departments {
+                if department.id == *department_id {
+                    return Ok((partner, department));
+                }
+            }
+        }
+        Err(bad_request("部門が見つかりません。"))
+    }
+
+    fn list_billings(&self, query: &HashMap<String, String>) -> Reply {
+        let (billings, meta) = paginate(&self.billings, query)?;
+        Ok(json(StatusCode::Ok, &Billings { meta, billings }))
+    }
+
+    fn search_billings(&self, query: &HashMap<String, String>) -> Reply {
+        let q = query.get("q").cloned().unwrap_or_default();
+        let range_key = query
+            .get("range_key")
+            .cloned()
+            .unwrap_or_else(|| "billing_date".into());
+        let from = parse_date(query.get("from"))?;
+        let to = parse_date(query.get("to"))?;
+
+        let mut hits = Vec::new();
+        for billing in &self.billings {
+            let date = match range_key.as_str() {
+                "billing_date" => billing.billing_date,
+                "due_date" => billing.due_date,
+                "sales_date" => billing.sales_date,
+                "created_at" => billing.created_at.date().naive_local(),
+                "updated_at" => billing.updated_at.date().naive_local(),
+                _ => return Err(bad_request("range_keyが不正です。")),
+            };
+            if from.map_or(false, |from| date < from) || to.map_or(false, |to| date > to) {
+                continue;
+            }
+            if q.is_empty() || matches(billing, &q) {
+                hits.push(billing.clone());
+            }
+        }
+
+        let (billings, meta) = paginate(&hits, query)?;
+        let today = today();
+        Ok(json(
+            StatusCode::Ok,
+            &BillingQueryResponse {
+                meta: BillingQueryMeta {
+                    total_count: meta.total_count,
+                    total_pages: meta.total_pages,
+                    current_page: meta.current_page,
+                    per_page: meta.per_page,
+                    condition: Condition {
+                        query: q,
+                        range_key,
+                        from: from.unwrap_or_else(|| NaiveDate::from_ymd(1970, 1, 1)),
+                        to: to.unwrap_or(today),
+                    },
+                },
+                billings,
+            },
+        ))
+    }
+
+    fn create_billing(&mut self, req: NewBilling) -> Reply {
+        let mut quantities = self.quantities.clone();
+        let mut items = Vec::new();
+        for item in req.items {
+            let id = self.next_id();
+            items.push(new_billing_item(id, item, &mut quantities)?);
+        }
+        let id = self.next_id();
+        let billing_number = match req.billing_number {
+            Some(billing_number) => billing_number,
+            None => {
+                self.last_billing_number += 1;
+                self.last_billing_number.to_string()
+            }
+        };
+
+        let now = now();
+        let billing_date = req.billing_date.unwrap_or_else(today);
+        let mut billing = Billing {
+            id,
+            partner_id: PartnerId::default(),
+            department_id: DepartmentId::default(),
+            partner_name: String::new(),
+            partner_name_suffix: String::new(),
+            partner_detail: String::new(),
+            member_id: "FAKEMEMBER".into(),
+            member_name: None,
+            office_name: self.office.name.clone(),
+            office_detail: office_detail(&self.office),
+            registration_number: req.registration_number
+                .or_else(|| self.office.registration_number.clone()),
+            title: req.title,
+            excise_price: Yen(0),
+            subtotal: Yen(0),
+            memo: req.memo,
+            payment_condition: req.payment_condition,
+            total_price: Yen(0),
+            tax_summaries: Vec::new(),
+            billing_date,
+            due_date: req.due_date
+                .unwrap_or_else(|| end_of_next_month(billing_date)),
+            sales_date: req.sales_date.unwrap_or(billing_date),
+            created_at: now,
+            updated_at: now,
+            billing_number,
+            note: req.note,
+            document_name: req.document_name.unwrap_or_default(),
+            tags: split_tags(req.tags),
+            status: Status::default(),
+            items,
+        };
+        self.set_department(&mut billing, &req.department_id)?;
+        recalculate(&mut billing, &quantities)?;
+        self.quantities = quantities;
+        self.billings.push(billing.clone());
+        Ok(json(StatusCode::Created, &billing))
+    }
+
+    fn update_billing(&mut self, id: &str, req: UpdateBilling) -> Reply {
+        let i = self.billing_index(id)?;
+        let mut new_ids = Vec::new();
+        for item in &req.items {
+            if item.id.is_none() {
+                new_ids.push(self.next_id());
+            }
+        }
+        let mut new_ids = new_ids.into_iter();
+
+        let mut billing = self.billings[i].clone();
+        let mut quantities = self.quantities.clone();
+        if let Patch::Set(ref department_id) = req.department_id {
+            self.set_department(&mut billing, department_id)?;
+        }
+        set_opt(&mut billing.title, req.title);
+        set(&mut billing.billing_number, req.billing_number);
+        set_opt(&mut billing.payment_condition, req.payment_condition);
+        set_opt(&mut billing.note, req.note);
+        set(&mut billing.billing_date, req.billing_date);
+        set(&mut billing.due_date, req.due_date);
+        set(&mut billing.sales_date, req.sales_date);
+        set_opt(&mut billing.memo, req.memo);
+        set(&mut billing.document_name, req.document_name);
+        set_opt(&mut billing.registration_number, req.registration_number);
+        if !req.tags.is_unchanged() {
+            billing.tags = split_tags(req.tags.into_value());
+        }
+        for item in req.items {
+            match item.id.clone() {
+                Some(ref id) => {
+                    let index = billing
+                        .items
+                        .iter()
+                        .position(|billing_item| &billing_item.id == id)
+                        .ok_or_else(|| bad_request("品目が見つかりません。"))?;
+                    if item._destroy {
+                        billing.items.remove(index);
+                        quantities.remove(id);
+                    } else {
+                        update_billing_item(&mut billing.items[index], item, &mut quantities)?;
+                    }
+                }
+                None => {
+                    let id = new_ids.next().expect("an id is reserved for each new item");
+                    if !item._destroy {
+                        let new_item = NewBillingItem {
+                            id: None,
+                            name: item.name.into_value(),
+                            code: item.code.into_value(),
+                            detail: item.detail.into_value(),
+                            quantity: item.quantity.into_value(),
+                            unit_price: item.unit_price.into_value(),
+                            unit: item.unit.into_value(),
+                            excise: item.excise.into_value().unwrap_or_default(),
+                        };
+                        billing
+                            .items
+                            .push(new_billing_item(id, new_item, &mut quantities)?);
+                    }
+                }
+            }
+        }
+        billing.updated_at = now();
+        recalculate(&mut billing, &quantities)?;
+        self.quantities = quantities;
+        self.billings[i] = billing.clone();
+        Ok(json(StatusCode::Ok, &billing))
+    }
+
+    /// 請求先の部門を設定する
+    fn set_department(
+        &self,
+        billing: &mut Billing,
+        department_id: &DepartmentId,
+    ) -> ::std::result::Result<(), HttpResponse> {
+        let (partner, department) = self.find_department(department_id)?;
+        billing.partner_id = partner.id.clone();
+        billing.department_id = department.id.clone();
+        billing.partner_name = partner.name.clone();
+        billing.partner_name_suffix = partner.name_suffix.clone();
+        billing.partner_detail = department_detail(department);
+        Ok(())
+    }
+
+    fn posting_billing(&mut self, id: &str) -> Reply {
+        let i = self.billing_index(id)?;
+        if self.billings[i].status.posting == PostingStatus::Waiting {
+            return Err(error(
+                StatusCode::UnprocessableEntity,
+                "既に郵送依頼されています。",
+            ));
+        }
+        self.billings[i].status.posting = PostingStatus::Waiting;
+        let history = SentHistory {
+            operator_id: "FAKEOPERATOR".into(),
+            type_: "郵送".into(),
+            document_type: "請求書".into(),
+            document_id: id.into(),
+            from: self.office.name.clone(),
+            to: self.billings[i].partner_name.clone(),
+            cc: String::new(),
+            sent_at: now().format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string(),
+        };
+        self.sent_history.push(history);
+        Ok(no_content())
+    }
+
+    fn cancel_posting_billing(&mut self, id: &str) -> Reply {
+        let i = self.billing_index(id)?;
+        if self.billings[i].status.posting != PostingStatus::Waiting {
+            return Err(error(
+                StatusCode::UnprocessableEntity,
+                "郵送依頼されていません。",
+            ));
+        }
+        self.billings[i].status.posting = PostingStatus::Cancelled;
+        Ok(no_content())
+    }
+
+    fn create_item(&mut self, req: NewItem) -> Reply {
+        if req.name.is_empty() {
+            return Err(bad_request("名前を入力してください。"));
+        }
+        let now = now();
+        let item = Item {
+            id: self.next_id(),
+            code: req.code,
+            name: req.name,
+            detail: req.detail,
+            quantity: req.quantity,
+            unit_price: req.unit_price,
+            unit: req.unit,
+            price: Yen(0),
+            excise: req.excise.unwrap_or_default(),
+            created_at: now,
+            updated_at: now,
+        };
+        let item = with_item_price(item)?;
+        self.items.push(item.clone());
+        Ok(json(StatusCode::Created, &item))
+    }
+
+    fn update_item(&mut self, id: &str, req: UpdateItem) -> Reply {
+        let i = self.item_index(id)?;
+        let mut item = self.items[i].clone();
+        set(&mut item.name, req.name);
+        set_opt(&mut item.code, req.code);
+        set_opt(&mut item.detail, req.detail);
+        set_opt(&mut item.unit_price, req.unit_price);
+        set_opt(&mut item.unit, req.unit);
+        set_opt(&mut item.quantity, req.quantity);
+        set(&mut item.excise, req.excise);
+        item.updated_at = now();
+        let item = with_item_price(item)?;
+        self.items[i] = item.clone();
+        Ok(json(StatusCode::Ok, &item))
+    }
+}
+
+#[derive(Deserialize)]
+struct PartnerRequest<T> {
+    partner: T,
+}
+
+#[derive(Deserialize)]
+struct BillingRequest<T> {
+    billing: T,
+}
+
+/// 空にできない項目なので`Patch::Clear`は無視する
+fn set<T>(field: &mut T, value: Patch<T>) {
+    if let Patch::Set(value) = value {
+        *field = value;
+    }
+}
+
+/// `Patch::Clear`なら空文字列にする
+fn set_str(field: &mut String, value: Patch<String>) {
+    match value {
+        Patch::Unchanged => {}
+        Patch::Clear => field.clear(),
+        Patch::Set(value) => *field = value,
+    }
+}
+
+fn set_opt<T>(field: &mut Option<T>, value: Patch<T>) {
+    value.apply(field);
+}
+
+/// 検索文字列が請求書の件名などに含まれるか
+fn matches(billing: &Billing, q: &str) -> bool {
+    let fields = [
+        billing.title.as_ref(),
+        Some(&billing.partner_name),
+        Some(&billing.billing_number),
+        billing.memo.as_ref(),
+        billing.note.as_ref(),
+    ];
+    fields
+        .iter()
+        .any(|field| field.map_or(false, |field| field.contains(q)))
+}
+
+fn new_billing_item(
+    id: BillingItemId,
+    item: NewBillingItem,
+    quantities: &mut Quantities,
+) -> ::std::result::Result<BillingItem, HttpResponse> {
+    let now = now();
+    let quantity = match parse_quantity(item.quantity.as_ref())? {
+        Some(exact) => {
+            quantities.insert(id.clone(), exact);
+            whole_quantity(exact)
+        }
+        None => None,
+    };
+    Ok(BillingItem {
+        id,
+        code: item.code,
+        name: item.name,
+        detail: item.detail,
+        quantity,
+        unit_price: item.unit_price,
+        unit: item.unit,
+        price: None,
+        display_order: 0,
+        excise: item.excise,
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+fn update_billing_item(
+    billing_item: &mut BillingItem,
+    item: UpdateBillingItem,
+    quantities: &mut Quantities,
+) -> ::std::result::Result<(), HttpResponse> {
+    set_opt(&mut billing_item.name, item.name);
+    set_opt(&mut billing_item.code, item.code);
+    set_opt(&mut billing_item.detail, item.detail);
+    let exact = match item.quantity {
+        Patch::Unchanged => Patch::Unchanged,
+        Patch::Clear => Patch::Clear,
+        Patch::Set(ref quantity) => Patch::from(parse_quantity(Some(quantity))?),
+    };
+    match exact {
+        Patch::Unchanged => {}
+        Patch::Clear => {
+            quantities.remove(&billing_item.id);
+            billing_item.quantity = None;
+        }
+        Patch::Set(exact) => {
+            quantities.insert(billing_item.id.clone(), exact);
+            billing_item.quantity = whole_quantity(exact);
+        }
+    }
+    set_opt(&mut billing_item.unit_price, item.unit_price);
+    set_opt(&mut billing_item.unit, item.unit);
+    set(&mut billing_item.excise, item.excise);
+    billing_item.updated_at = now();
+    Ok(())
+}
+
+/// 正確な数量で金額を計算する品目
+struct ExactItem<'a> {
+    item: &'a BillingItem,
+    quantity: Option<(i64, u32)>,
+}
+
+impl<'a> TaxableItem for ExactItem<'a> {
+    fn quantity(&self) -> Result<(i64, u32)> {
+        match self.quantity {
+            Some(quantity) => Ok(quantity),
+            None => TaxableItem::quantity(self.item),
+        }
+    }
+
+    fn unit_price(&self) -> Option<Yen> {
+        self.item.unit_price
+    }
+
+    fn tax_category(&self) -> TaxCategory {
+        self.item.excise.clone()
+    }
+}
+
+/// 品目の金額と請求書の合計を計算し直す
+fn recalculate(
+    billing: &mut Billing,
+    quantities: &Quantities,
+) -> ::std::result::Result<(), HttpResponse> {
+    let items: Vec<ExactItem> = billing
+        .items
+        .iter()
+        .map(|item| ExactItem {
+            item,
+            quantity: quantities.get(&item.id).cloned(),
+        })
+        .collect();
+    let totals = TaxCalculator::default()
+        .items(&items)
+        .map_err(|_| bad_request("金額を計算できません。"))?;
+    for (display_order, (item, line)) in billing.items.iter_mut().zip(totals.lines).enumerate() {
+        item.display_order = display_order as u32;
+        item.price = item.unit_price.map(|_| line.price);
+    }
+    billing.subtotal = totals.subtotal;
+    billing.excise_price = totals.excise_price;
+    billing.total_price = totals.total_price;
+    billing.tax_summaries = totals.summaries;
+    Ok(())
+}
+
+fn with_item_price(mut item: Item) -> ::std::result::Result<Item, HttpResponse> {
+    item.price = item.unit_price
+        .unwrap_or_default()
+        .checked_mul(i64::from(item.quantity.unwrap_or(1)))
+        .ok_or_else(|| bad_request("金額を計算できません。"))?;
+    Ok(item)
+}
+
+fn split_tags(tags: Option<String>) -> Vec<String> {
+    tags.map(|tags| {
+        tags.split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(Into::into)
+            .collect()
+    }).unwrap_or_default()
+}
+
+fn department_detail(department: &Department) -> String {
+    let lines = [
+        department.zip.as_ref().map(|zip| format!("〒{}", zip)),
+        Some(format!(
+            "{}{}",
+            department.prefecture,
+            department.address1.as_ref().map_or("", String::as_str)
+        )),
+        department.address2.clone(),
+        department.name.clone(),
+        department.person_name.clone(),
+    ];
+    join_lines(&lines)
+}
+
+fn office_detail(office: &Office) -> String {
+    let lines = [
+        Some(format!("〒{}", office.zip)),
+        Some(format!("{}{}", office.prefecture, office.address1)),
+        Some(office.address2.clone()),
+        Some(format!("TEL: {}", office.tel)),
+    ];
+    join_lines(&lines)
+}
+
+fn join_lines(lines: &[Option<String>]) -> String {
+    lines
+        .iter()
+        .filter_map(|line| line.as_ref())
+        .filter(|line| !line.is_empty())
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 一覧の1ページ分とメタデータ
+fn paginate<T: Clone>(
+    all: &[T],
+    query: &HashMap<String, String>,
+) -> ::std::result::Result<(Vec<T>, Meta), HttpResponse> {
+    let page = parse_param(query, "page", 1)?;
+    let per_page = parse_param(query, "per_page", DEFAULT_PER_PAGE)?;
+    if page == 0 || per_page == 0 || per_page > MAX_PER_PAGE {
+        return Err(bad_request("pageまたはper_pageが範囲外です。"));
+    }
+    let total_count = all.len() as u32;
+    let total_pages = (total_count + per_page - 1) / per_page;
+    let start = (page - 1)
+        .checked_mul(per_page)
+        .ok_or_else(|| bad_request("pageまたはper_pageが範囲外です。"))? as usize;
+    let items = all.iter().skip(start).take(per_page as usize).cloned().collect();
+    let meta = Meta {
+        total_count,
+        total_pages,
+        current_page: page.to_string(),
+        per_page: per_page.to_string(),
+    };
+    Ok((items, meta))
+}
+
+fn parse_param(
+    query: &HashMap<String, String>,
+    name: &str,
+    default: u32,
+) -> ::std::result::Result<u32, HttpResponse> {
+    match query.get(name) {
+        Some(value) => value
+            .parse()
+            .map_err(|_| bad_request(&format!("{}が不正です。", name))),
+        None => Ok(default),
+    }
+}
+
+/// 数量を`tax::parse_quantity`と同じ規則で読む。負の数量は受け付けない
+fn parse_quantity(
+    value: Option<&String>,
+) -> ::std::result::Result<Option<(i64, u32)>, HttpResponse> {
+    match value {
+        Some(value) if !value.is_empty() => match tax::parse_quantity(value) {
+            Ok(exact) if exact.0 >= 0 && whole_quantity(exact).is_some() => Ok(Some(exact)),
+            _ => Err(bad_request("数量は数値で入力してください。")),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// 数量の整数部分。`u32`に収まらなければ`None`
+fn whole_quantity((scaled, scale): (i64, u32)) -> Option<u32> {
+    let whole = scaled / 10i64.pow(scale);
+    if 0 <= whole && whole <= i64::from(u32::max_value()) {
+        Some(whole as u32)
+    } else {
+        None
+    }
+}
+
+fn parse_date(value: Option<&String>) -> ::std::result::Result<Option<NaiveDate>, HttpResponse> {
+    match value {
+        Some(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| bad_request("日付が不正です。")),
+        None => Ok(None),
+    }
+}
+
+/// 日本時間の現在時刻
+fn now() -> DateTime<FixedOffset> {
+    Utc::now().with_timezone(&FixedOffset::east(9 * 3600))
+}
+
+fn today() -> NaiveDate {
+    now().date().naive_local()
+}
+
+/// 翌月末。支払期限のデフォルト
+fn end_of_next_month(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() >= 11 {
+        (date.year() + 1, date.month() - 10)
+    } else {
+        (date.year(), date.month() + 2)
+    };
+    NaiveDate::from_ymd(year, month, 1).pred()
+}
+
+fn decode<T: DeserializeOwned>(body: &Option<Vec<u8>>) -> ::std::result::Result<T, HttpResponse> {
+    let body = body.as_ref()
+        .ok_or_else(|| bad_request("リクエストボディがありません。"))?;
+    serde_json::from_slice(body).map_err(|e| bad_request(&e.to_string()))
+}
+
+fn response(status: StatusCode, content_type: ContentType, body: Vec<u8>) -> HttpResponse {
+    let mut headers = Headers::new();
+    headers.set(content_type);
+    HttpResponse {
+        status,
+        headers,
+        body,
+    }
+}
+
+fn json<T: Serialize>(status: StatusCode, value: &T) -> HttpResponse {
+    match serde_json::to_vec(value) {
+        Ok(body) => response(status, ContentType::json(), body),
+        Err(e) => error(StatusCode::InternalServerError, &e.to_string()),
+    }
+}
+
+fn no_content() -> HttpResponse {
+    HttpResponse {
+        status: StatusCode::NoContent,
+        headers: Headers::new(),
+        body: Vec::new(),
+    }
+}
+
+fn billing_pdf(billing: &Billing) -> HttpResponse {
+    let body = format!(
+        "%PDF-1.4\n% fake billing {} ({})\n%%EOF\n",
+        billing.id,
+        billing.total_price
+    );
+    let content_type = ContentType("application/pdf".parse().expect("valid mime type"));
+    response(StatusCode::Ok, content_type, body.into_bytes())
+}
+
+fn error(status: StatusCode, message: &str) -> HttpResponse {
+    let error = ApiError {
+        code: status.as_u16().to_string(),
+        errors: vec![ErrorMessage { message: message.into() }],
+    };
+    let body = serde_json::to_vec(&error).expect("ApiError always serializes");
+    response(status, ContentType::json(), body)
+}
+
+fn bad_request(message: &str) -> HttpResponse {
+    error(StatusCode::BadRequest, message)
+}
+
+fn not_found() -> HttpResponse {
+    error(StatusCode::NotFound, "リソースが見つかりません。")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use reqwest::Url;
+
+    use super::*;
+    use duplicate::{DuplicateOptions, Period};
+    use id::{BillingId, ItemId};
+    use search::BillingSearch;
+    use Error;
+
+    fn get(server: &FakeServer, path: &str) -> HttpResponse {
+        let mut headers = Headers::new();
+        headers.set(Authorization(Bearer {
+            token: server.access_token().into(),
+        }));
+        let request = HttpRequest {
+            method: Method::Get,
+            url: Url::parse("https://invoice.moneyforward.com")
+                .unwrap()
+                .join(path)
+                .unwrap(),
+            headers,
+            body: None,
+        };
+        server.send(request).unwrap()
+    }
+
+    fn status<T: ::std::fmt::Debug>(result: Result<T>) -> Option<StatusCode> {
+        result.unwrap_err().status()
+    }
+
+    fn create_billing(client: &mut Client, quantity: &str, excise: TaxCategory) -> Billing {
+        let partner = client
+            .create_partner(NewPartner::builder("サンプル取引先").build())
+            .unwrap();
+        client
+            .create_billing(
+                NewBilling::builder(partner.departments[0].id.clone())
+                    .billing_date(NaiveDate::from_ymd(2017, 9, 30))
+                    .push_item(
+                        NewBillingItem::builder("商品A", Yen(1000))
+                            .quantity(quantity)
+                            .excise(excise)
+                            .build(),
+                    )
+                    .due_in_days(30)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn updates_office_and_partners() {
+        let server = FakeServer::new();
+        let mut client = server.client().unwrap();
+
+        let office = client
+            .update_office(UpdateOffice {
+                zip: Patch::Set("123-4567".into()),
+                registration_number: Patch::Set("T7000012050002".parse().unwrap()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(office, client.get_office().unwrap());
+        assert_eq!(office, server.office());
+
+        let partner = client
+            .create_partner(NewPartner::builder("サンプル取引先").build())
+            .unwrap();
+        assert_eq!(partner.name_suffix, DEFAULT_NAME_SUFFIX);
+        let partner = client
+            .update_partner(
+                &partner.id,
+                UpdatePartner {
+                    memo: Patch::Set("更新しました".into()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(partner, client.get_partner(&partner.id).unwrap());
+        assert_eq!(client.partners().iter().count(), 1);
+
+        client.delete_partner(&partner.id).unwrap();
+        assert!(server.partners().is_empty());
+    }
+
+    #[test]
+    fn totals_decimal_quantities_exactly() {
+        let server = FakeServer::new();
+        let mut client = server.client().unwrap();
+        let billing = create_billing(&mut client, "1.5", TaxCategory::Reduced);
+
+        assert_eq!(billing.subtotal, Yen(1500));
+        assert_eq!(billing.excise_price, Yen(120));
+        assert_eq!(billing.total_price, Yen(1620));
+        // BillingItem::quantityは整数なので切り捨てる
+        assert_eq!(billing.items[0].quantity, Some(1));
+        assert_eq!(billing.due_date, NaiveDate::from_ymd(2017, 10, 30));
+        tax::check_summaries(&billing).unwrap();
+
+        // 小数の数量は更新後も保たれる
+        let billing = client
+            .update_billing(
+                &billing.id,
+                UpdateBilling {
+                    title: Patch::Set("サンプル件名".into()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(billing.total_price, Yen(1620));
+
+        assert_eq!(
+            status(client.create_billing(
+                NewBilling::builder(billing.department_id.clone())
+                    .push_item(
+                        NewBillingItem::builder("商品A", Yen(1000))
+                            .quantity("-1")
+                            .build()
+                    )
+                    .build()
+                    .unwrap(),
+            )),
+            Some(StatusCode::BadRequest)
+        );
+    }
+
+    #[test]
+    fn updates_and_searches_billings() {
+        let server = FakeServer::new();
+        let mut client = server.client().unwrap();
+        let billing = create_billing(&mut client, "1.5", TaxCategory::Reduced);
+
+        let billing = client
+            .update_billing(
+                &billing.id,
+                UpdateBilling {
+                    title: Patch::Set("サンプル件名".into()),
+                    items: vec![
+                        UpdateBillingItem {
+                            id: Some(billing.items[0].id.clone()),
+                            _destroy: true,
+                            ..Default::default()
+                        },
+                        UpdateBillingItem {
+                            name: Patch::Set("新しい品目".into()),
+                            unit_price: Patch::Set(Yen(500)),
+                            ..Default::default()
+                        },
+                    ],
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(billing.items.len(), 1);
+        assert_eq!(billing.total_price, Yen(550));
+
+        let found = client
+            .search_billings(1, 100, &BillingSearch::new().query("サンプル"))
+            .unwrap();
+        assert_eq!(found.billings, vec![billing.clone()]);
+        let found = client
+            .search_billings(1, 100, &BillingSearch::new().query("見つからない"))
+            .unwrap();
+        assert!(found.billings.is_empty());
+        assert_eq!(client.billings().iter().count(), 1);
+
+        let mut pdf = Vec::new();
+        client
+            .get_billing_pdf(&billing.id)
+            .unwrap()
+            .read_to_end(&mut pdf)
+            .unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+
+        let copy = client
+            .duplicate_billing(
+                &billing.id,
+                &DuplicateOptions::new()
+                    .shift(Period::Months(1))
+                    .billing_number("2017-10-001"),
+            )
+            .unwrap();
+        assert_eq!(copy.billing_date, NaiveDate::from_ymd(2017, 10, 31));
+        assert_eq!(copy.due_date, NaiveDate::from_ymd(2017, 11, 30));
+        assert_eq!(copy.billing_number, "2017-10-001");
+        assert_eq!(copy.total_price, billing.total_price);
+
+        client.delete_billing(&copy.id).unwrap();
+        client.delete_billing(&billing.id).unwrap();
+        assert!(server.billings().is_empty());
+    }
+
+    #[test]
+    fn tracks_posting_state() {
+        let server = FakeServer::new();
+        let mut client = server.client().unwrap();
+        let billing = create_billing(&mut client, "1", TaxCategory::Standard);
+        assert_eq!(billing.status.posting, PostingStatus::NotPosted);
+
+        assert_eq!(
+            status(client.cancel_posting_billing(&billing.id)),
+            Some(StatusCode::UnprocessableEntity)
+        );
+
+        client.posting_billing(&billing.id).unwrap();
+        let posted = client.get_billing(&billing.id).unwrap();
+        assert_eq!(posted.status.posting, PostingStatus::Waiting);
+        assert_eq!(
+            status(client.posting_billing(&billing.id)),
+            Some(StatusCode::UnprocessableEntity)
+        );
+
+        client.cancel_posting_billing(&billing.id).unwrap();
+        let cancelled = client.get_billing(&billing.id).unwrap();
+        assert_eq!(cancelled.status.posting, PostingStatus::Cancelled);
+        assert_eq!(
+            status(client.cancel_posting_billing(&billing.id)),
+            Some(StatusCode::UnprocessableEntity)
+        );
+
+        let history = server.sent_history();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].document_id, billing.id.as_str());
+        assert_eq!(history[0].to, billing.partner_name);
+        assert_eq!(client.sent_histories().iter().count(), 1);
+    }
+
+    #[test]
+    fn prices_items() {
+        let server = FakeServer::new();
+        let mut client = server.client().unwrap();
+        let item = client
+            .create_item(NewItem {
+                name: "商品B".into(),
+                unit_price: Some(Yen(300)),
+                ..Default::default()
+            })
+            .unwrap();
+        let item = client
+            .update_item(
+                &item.id,
+                UpdateItem {
+                    quantity: Patch::Set(3),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(item.price, Yen(900));
+        assert_eq!(client.list_items().unwrap().items, vec![item.clone()]);
+        client.delete_item(&item.id).unwrap();
+        assert!(server.items().is_empty());
+    }
+
+    #[test]
+    fn unknown_ids_are_not_found() {
+        let server = FakeServer::new();
+        let mut client = server.client().unwrap();
+        let not_found = Some(StatusCode::NotFound);
+
+        assert_eq!(status(client.get_partner(&PartnerId::new("NOPE"))), not_found);
+        assert_eq!(status(client.delete_partner(&PartnerId::new("NOPE"))), not_found);
+        assert_eq!(status(client.get_billing(&BillingId::new("NOPE"))), not_found);
+        assert_eq!(status(client.get_billing_pdf(&BillingId::new("NOPE"))), not_found);
+        assert_eq!(status(client.delete_billing(&BillingId::new("NOPE"))), not_found);
+        assert_eq!(status(client.posting_billing(&BillingId::new("NOPE"))), not_found);
+        assert_eq!(status(client.get_item(&ItemId::new("NOPE"))), not_found);
+        assert_eq!(status(client.delete_item(&ItemId::new("NOPE"))), not_found);
+        assert_eq!(get(&server, "/api/v1/unknown.json").status, StatusCode::NotFound);
+        assert_eq!(get(&server, "/other").status, StatusCode::NotFound);
+    }
+
+    #[test]
+    fn rejects_pages_out_of_range() {
+        let server = FakeServer::new();
+        for path in &[
+            "/api/v1/partners.json?page=0",
+            "/api/v1/partners.json?per_page=0",
+            "/api/v1/billings.json?per_page=101",
+            "/api/v1/billings/search.json?page=x",
+            "/api/v1/sent_history.json?page=4294967295&per_page=100",
+        ] {
+            assert_eq!(get(&server, path).status, StatusCode::BadRequest, "{}", path);
+        }
+
+        // 範囲内なら最後のページより後でも空の一覧を返す
+        let res = get(&server, "/api/v1/partners.json?page=3&per_page=100");
+        assert_eq!(res.status, StatusCode::Ok);
+        let partners: Partners = serde_json::from_slice(&res.body).unwrap();
+        assert!(partners.partners.is_empty());
+        assert_eq!(partners.meta.total_pages, 0);
+    }
+
+    #[test]
+    fn paginates_lists() {
+        let server = FakeServer::new();
+        let mut client = server.client().unwrap();
+        for name in &["A", "B", "C"] {
+            client
+                .create_partner(NewPartner::builder(*name).build())
+                .unwrap();
+        }
+        let page = client.list_partners(2, 2).unwrap();
+        assert_eq!(page.meta.total_count, 3);
+        assert_eq!(page.meta.total_pages, 2);
+        assert_eq!(page.meta.current_page, "2");
+        assert_eq!(page.partners.len(), 1);
+        assert_eq!(page.partners[0].name, "C");
+    }
+
+    #[test]
+    fn rejects_other_access_tokens() {
+        let server = FakeServer::with_access_token("right");
+        let mut client = ClientBuilder::new()
+            .transport(server.clone())
+            .build("wrong")
+            .unwrap();
+        match client.get_office() {
+            Err(ref e @ Error::Api { .. }) => assert_eq!(e.status(), Some(StatusCode::Unauthorized)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! リソースのID
+//!
+//! IDは全て文字列ですが、リソースごとに別の型にしているので取引先IDで請求書を取得するような取り違えはコンパイルできません。
+//! JSONでは文字列のまま読み書きします。
+
+use std::fmt;
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+macro_rules! id_type {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn new<S: Into<String>>(id: S) -> Self {
+                $name(id.into())
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            /// IDがまだ振られていないか
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                $name(id)
+            }
+        }
+
+        impl<'a> From<&'a str> for $name {
+            fn from(id: &'a str) -> Self {
+                $name(id.into())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_str(&self.0)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                String::deserialize(deserializer).map($name)
+            }
+        }
+    };
+}
+
+id_type! {
+    /// 取引先ID e.g. "ABCDEFGHIJKLMNOPQRST789"
+    PartnerId
+}
+
+id_type! {
+    /// 部門ID e.g. "ABCDEFGHIJKLMNOPQRST012"
+    DepartmentId
+}
+
+id_type! {
+    /// 請求書ID e.g. "ABCDEFGHIJKLMNOPQRST123"
+    BillingId
+}
+
+id_type! {
+    /// 請求書の品目ID e.g. "ABCDEFGHIJKLMNOPQRST012"
+    BillingItemId
+}
+
+id_type! {
+    /// 品目ID e.g. "ABCDEFGHIJKLMNOPQRST012"
+    ItemId
+}
+
+id_type! {
+    /// 担当者ID e.g. "ABCDEFGHIJKLMNOPQRST345"
+    MemberId
+}
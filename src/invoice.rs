@@ -0,0 +1,307 @@
+//! 適格請求書（インボイス制度）
+//!
+//! 2023年10月からの適格請求書には発行者の登録番号と税率ごとの内訳が必要です。
+//! 送付や郵送の前に`check`で足りない記載事項を確かめられます。
+//!
+//! ```ignore
+//! let violations = invoice::check(&billing);
+//! if violations.is_empty() {
+//!     client.posting_billing(&billing.id)?;
+//! }
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use model::Billing;
+use tax::{self, Rounding, TaxCategory};
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// 適格請求書発行事業者の登録番号 e.g. "T1234567890123"
+///
+/// "T"と13桁の数字からなり、先頭の数字がチェックデジットです。
+/// サーバから読んだ番号は正しくなくてもそのまま持つので、`is_valid`か`check`で確かめてください。
+pub struct RegistrationNumber(String);
+
+impl RegistrationNumber {
+    /// "T"から始まる14文字
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// "T"を除いた13桁の数字
+    pub fn digits(&self) -> &str {
+        self.0.get(1..).unwrap_or("")
+    }
+
+    /// 形式とチェックデジットが正しいか
+    pub fn is_valid(&self) -> bool {
+        self.0.parse::<RegistrationNumber>().is_ok()
+    }
+}
+
+impl fmt::Display for RegistrationNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for RegistrationNumber {
+    type Err = ParseRegistrationNumberError;
+
+    /// 区切りのハイフンや空白は読み飛ばす e.g. "T1-2345-6789-0123"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseRegistrationNumberError(s.into());
+        let cleaned: String = s.trim()
+            .chars()
+            .filter(|&c| c != '-' && !c.is_whitespace())
+            .collect();
+        if !cleaned.starts_with('T') {
+            return Err(error());
+        }
+        let digits: Vec<u32> = cleaned[1..].chars().filter_map(|c| c.to_digit(10)).collect();
+        if digits.len() != 13 || cleaned.len() != 14 {
+            return Err(error());
+        }
+        if digits[0] != check_digit(&digits[1..]) {
+            return Err(error());
+        }
+        Ok(RegistrationNumber(cleaned))
+    }
+}
+
+/// 法人番号と同じ方式のチェックデジット
+///
+/// 下の桁から数えて奇数桁は1倍、偶数桁は2倍した和を9で割った余りを9から引く。
+fn check_digit(base: &[u32]) -> u32 {
+    let sum: u32 = base.iter()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| if i % 2 == 0 { *digit } else { digit * 2 })
+        .sum();
+    9 - sum % 9
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// 登録番号として読めない文字列だった
+pub struct ParseRegistrationNumberError(String);
+
+impl fmt::Display for ParseRegistrationNumberError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid registration number: {:?}", self.0)
+    }
+}
+
+impl StdError for ParseRegistrationNumberError {
+    fn description(&self) -> &str {
+        "invalid registration number"
+    }
+}
+
+impl Serialize for RegistrationNumber {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for RegistrationNumber {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+/// 未登録の事業所はnullか空文字列を返すので、どちらも`None`として読む
+///
+/// 正しくない番号でも請求書や事業所を読めるように、読めなければそのままの値で持つ。
+pub(crate) fn deserialize_optional<'de, D>(
+    deserializer: D,
+) -> Result<Option<RegistrationNumber>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(ref s) if !s.trim().is_empty() => Ok(Some(
+            s.parse()
+                .unwrap_or_else(|_| RegistrationNumber(s.trim().into())),
+        )),
+        _ => Ok(None),
+    }
+}
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// 適格請求書として満たしていない記載事項
+pub enum Violation {
+    /// 発行者の名称がない
+    MissingIssuerName,
+    /// 発行者の登録番号がない
+    MissingRegistrationNumber,
+    /// 発行者の登録番号の形式かチェックデジットが正しくない
+    InvalidRegistrationNumber(RegistrationNumber),
+    /// 交付を受ける事業者の名称がない
+    MissingRecipientName,
+    /// 品目がない
+    MissingItems,
+    /// 品名のない品目がある。値は品目の位置
+    MissingItemName(usize),
+    /// 品目に現れる税区分の内訳がない
+    MissingTaxSummary(TaxCategory),
+    /// 内訳の消費税額が税率から求めた額と合わない
+    TaxAmountMismatch(TaxCategory),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::MissingIssuerName => write!(f, "発行者の名称がありません"),
+            Violation::MissingRegistrationNumber => write!(f, "登録番号がありません"),
+            Violation::InvalidRegistrationNumber(ref number) => {
+                write!(f, "登録番号{}が正しくありません", number)
+            }
+            Violation::MissingRecipientName => write!(f, "取引先の名称がありません"),
+            Violation::MissingItems => write!(f, "品目がありません"),
+            Violation::MissingItemName(index) => write!(f, "{}行目の品名がありません", index + 1),
+            Violation::MissingTaxSummary(ref category) => {
+                write!(f, "税率{}の内訳がありません", category)
+            }
+            Violation::TaxAmountMismatch(ref category) => {
+                write!(f, "税率{}の消費税額が合いません", category)
+            }
+        }
+    }
+}
+
+/// 請求書が適格請求書の記載事項を満たしているか確かめ、足りないものを返す
+///
+/// 取引年月日は常にあるので確かめません。
+/// 消費税額は端数の処理に依らず、切り捨てから切り上げまでの範囲にあれば良しとします。
+pub fn check(billing: &Billing) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    if billing.office_name.trim().is_empty() {
+        violations.push(Violation::MissingIssuerName);
+    }
+    match billing.registration_number {
+        None => violations.push(Violation::MissingRegistrationNumber),
+        Some(ref number) if !number.is_valid() => {
+            violations.push(Violation::InvalidRegistrationNumber(number.clone()))
+        }
+        Some(_) => {}
+    }
+    if billing.partner_name.trim().is_empty() {
+        violations.push(Violation::MissingRecipientName);
+    }
+    if billing.items.is_empty() {
+        violations.push(Violation::MissingItems);
+    }
+    for (index, item) in billing.items.iter().enumerate() {
+        let named = item.name
+            .as_ref()
+            .map_or(false, |name| !name.trim().is_empty());
+        if !named {
+            violations.push(Violation::MissingItemName(index));
+        }
+    }
+    violations.extend(
+        tax::missing_summaries(billing)
+            .into_iter()
+            .map(Violation::MissingTaxSummary),
+    );
+    for summary in &billing.tax_summaries {
+        if let TaxCategory::Unknown(_) = summary.excise {
+            continue;
+        }
+        let tax = |rounding| {
+            tax::TaxCalculator::new(tax::STANDARD_RATE, rounding)
+                .tax(&summary.excise, summary.subtotal)
+        };
+        let (lowest, highest) = (tax(Rounding::Down), tax(Rounding::Up));
+        // 税額を計算できないほど大きな小計も一致しないものとする
+        let matches = match (lowest, highest) {
+            (Ok(lowest), Ok(highest)) => {
+                let (lowest, highest) = (lowest.min(highest), lowest.max(highest));
+                lowest <= summary.excise_price && summary.excise_price <= highest
+            }
+            _ => false,
+        };
+        if !matches {
+            violations.push(Violation::TaxAmountMismatch(summary.excise.clone()));
+        }
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn digits(s: &str) -> Vec<u32> {
+        s.chars().filter_map(|c| c.to_digit(10)).collect()
+    }
+
+    #[test]
+    fn computes_check_digits() {
+        assert_eq!(check_digit(&digits("000012050002")), 7);
+        assert_eq!(check_digit(&digits("700110005901")), 8);
+        // 和が9の倍数なら9
+        assert_eq!(check_digit(&digits("000000000000")), 9);
+    }
+
+    #[test]
+    fn parses_valid_numbers() {
+        let number: RegistrationNumber = "T7000012050002".parse().unwrap();
+        assert_eq!(number.as_str(), "T7000012050002");
+        assert_eq!(number.digits(), "7000012050002");
+        assert!(number.is_valid());
+
+        let number: RegistrationNumber = " T8-7001-1000-5901 ".parse().unwrap();
+        assert_eq!(number.as_str(), "T8700110005901");
+    }
+
+    #[test]
+    fn rejects_wrong_check_digit() {
+        assert!("T1000012050002".parse::<RegistrationNumber>().is_err());
+    }
+
+    #[test]
+    fn rejects_missing_t() {
+        assert!("7000012050002".parse::<RegistrationNumber>().is_err());
+        assert!("t7000012050002".parse::<RegistrationNumber>().is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_length() {
+        assert!("T700001205000".parse::<RegistrationNumber>().is_err());
+        assert!("T70000120500021".parse::<RegistrationNumber>().is_err());
+        assert!("T".parse::<RegistrationNumber>().is_err());
+        assert!("T700001205000X".parse::<RegistrationNumber>().is_err());
+    }
+
+    #[test]
+    fn reads_invalid_numbers_from_the_server_as_is() {
+        let read = |json: &str| {
+            deserialize_optional(&mut serde_json::Deserializer::from_str(json)).unwrap()
+        };
+        assert_eq!(read("null"), None);
+        assert_eq!(read(r#""""#), None);
+        assert_eq!(
+            read(r#""T7000012050002""#),
+            Some("T7000012050002".parse().unwrap())
+        );
+
+        let number = read(r#""T1000012050002""#).unwrap();
+        assert_eq!(number.as_str(), "T1000012050002");
+        assert!(!number.is_valid());
+        assert_eq!(read(r#""T""#).unwrap().digits(), "");
+    }
+}
@@ -4,36 +4,100 @@ extern crate serde_json;
 #[macro_use]
 extern crate serde_derive;
 extern crate reqwest;
+extern crate rand;
+extern crate url;
 #[macro_use]
 extern crate log;
+#[cfg(feature = "async")]
+extern crate futures;
+#[cfg(feature = "async")]
+extern crate tokio_core;
 
 pub mod model;
+pub mod id;
+pub mod patch;
+pub mod error;
+mod client_builder;
+mod retry;
+mod rate_limit;
+pub mod pagination;
+pub mod auth;
+mod credentials;
+pub mod token_store;
+pub mod transport;
+pub mod cassette;
+pub mod search;
+pub mod money;
+pub mod tax;
+pub mod invoice;
+pub mod validate;
+pub mod builder;
+mod convert;
+pub mod duplicate;
+#[cfg(feature = "async")]
+pub mod async_client;
+#[cfg(feature = "fake")]
+pub mod fake;
+
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-use chrono::NaiveDate;
 use serde::de::DeserializeOwned;
 use serde::ser::Serialize;
-use reqwest::{Client as HttpClient, Url, Method};
+use reqwest::{Url, Method};
+use reqwest::header::Headers;
+use retry::Failure;
+use rate_limit::RateLimiter;
+use credentials::Credentials;
+use auth::Token;
+use transport::{HttpRequest, HttpResponse};
 pub use model::*;
-
-pub type ApiResult<T> = ::std::result::Result<T, ApiError>;
-pub type Result<T> = reqwest::Result<ApiResult<T>>;
-
-
-static SERVER: &str = "https://invoice.moneyforward.com/";
-
+pub use id::{BillingId, BillingItemId, DepartmentId, ItemId, MemberId, PartnerId};
+pub use patch::Patch;
+pub use error::Error;
+pub use client_builder::ClientBuilder;
+pub use retry::RetryPolicy;
+pub use rate_limit::RateLimit;
+pub use pagination::{Page, Pager};
+pub use transport::Transport;
+pub use search::{BillingSearch, RangeKey};
+pub use money::Yen;
+pub use tax::{Rounding, TaxCalculator, TaxCategory};
+pub use invoice::RegistrationNumber;
+pub use validate::Validate;
+pub use duplicate::{DuplicateOptions, Period};
+#[cfg(feature = "async")]
+pub use async_client::AsyncClient;
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+
+/// クライアント
+///
+/// クローンしたクライアントはレート制限を共有します。
+#[derive(Clone)]
 pub struct Client {
-    client: HttpClient,
-    server: Url,
-    token: String,
+    pub(crate) transport: Arc<Transport>,
+    pub(crate) headers: Headers,
+    pub(crate) server: Url,
+    pub(crate) credentials: Credentials,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) rate_limiter: Option<RateLimiter>,
 }
 
 impl Client {
-    pub fn new<S: Into<String>>(token: S) -> ::std::result::Result<Self, Box<::std::error::Error>> {
-        Ok(Self {
-            token: token.into(),
-            client: HttpClient::new().map_err(Box::new)?,
-            server: SERVER.parse().map_err(Box::new)?,
-        })
+    pub fn new<T: Into<Token>>(token: T) -> Result<Self> {
+        ClientBuilder::new().build(token)
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// 現在のアクセストークン
+    pub fn access_token(&self) -> String {
+        self.credentials.access_token()
     }
 }
 
@@ -43,21 +107,20 @@ impl Client {
     }
 
     pub fn update_office(&mut self, req: UpdateOffice) -> Result<Office> {
+        req.validate()?;
         self.patch_json("/api/v1/office", &req)
     }
 
     pub fn list_partners(&mut self, page: u32, per_page: u32) -> Result<Partners> {
-        // TBD: check if per_page <= 100 ?
+        validate::page(page, per_page)?;
+        let (page, per_page) = (page.to_string(), per_page.to_string());
         self.get_params(
             "/api/v1/partners.json",
-            &[
-                ("page", &page.to_string()),
-                ("per_page", &per_page.to_string()),
-            ],
+            &page_params(&page, &per_page),
         )
     }
 
-    pub fn get_partner(&mut self, id: &str) -> Result<Partner> {
+    pub fn get_partner(&mut self, id: &PartnerId) -> Result<Partner> {
         self.get(&format!("/api/v1/partners/{}.json", id))
     }
 
@@ -67,32 +130,32 @@ impl Client {
             partner: NewPartner,
         }
 
+        req.validate()?;
         self.post_json("/api/v1/partners", &Request { partner: req })
     }
 
-    pub fn update_partner(&mut self, id: &str, req: UpdatePartner) -> Result<Partner> {
+    pub fn update_partner(&mut self, id: &PartnerId, req: UpdatePartner) -> Result<Partner> {
         #[derive(Serialize)]
         struct Request {
             partner: UpdatePartner,
         }
+        req.validate()?;
         self.patch_json(
             &format!("/api/v1/partners/{}", id),
             &Request { partner: req },
         )
     }
 
-    pub fn delete_partner(&mut self, id: &str) -> Result<()> {
+    pub fn delete_partner(&mut self, id: &PartnerId) -> Result<()> {
         self.delete_void(&format!("/api/v1/partners/{}.json", id))
     }
 
     pub fn list_billings(&mut self, page: u32, per_page: u32) -> Result<Billings> {
-        // TBD: check if per_page <= 100 ?
+        validate::page(page, per_page)?;
+        let (page, per_page) = (page.to_string(), per_page.to_string());
         self.get_params(
             "/api/v1/billings.json",
-            &[
-                ("page", &page.to_string()),
-                ("per_page", &per_page.to_string()),
-            ],
+            &page_params(&page, &per_page),
         )
     }
 
@@ -100,40 +163,24 @@ impl Client {
         &mut self,
         page: u32,
         per_page: u32,
-        q: &str,
-        range_key: Option<&str>,
-        from: Option<NaiveDate>,
-        to: Option<NaiveDate>,
+        search: &BillingSearch,
     ) -> Result<BillingQueryResponse> {
-        let page = page.to_string();
-        let per_page = per_page.to_string();
-        let from = from.map(|date| date.to_string());
-        let to = to.map(|date| date.to_string());
-        let mut params: Vec<(&str, &str)> =
-            vec![("page", &page), ("per_page", &per_page), ("q", q)];
-        if let Some(range_key) = range_key {
-            params.push(("range_key", range_key));
-        }
-        if let Some(ref from) = from {
-            params.push(("from", from));
-        }
-        if let Some(ref to) = to {
-            params.push(("to", to));
-        }
-        self.get_params("/api/v1/billings/search.json", &params)
+        validate::page(page, per_page)?;
+        let params = search.params(page, per_page);
+        self.get_params("/api/v1/billings/search.json", &borrow_params(&params))
     }
 
-    pub fn get_billing(&mut self, id: &str) -> Result<Billing> {
+    pub fn get_billing(&mut self, id: &BillingId) -> Result<Billing> {
         self.get(&format!("/api/v1/billings/{}.json", id))
     }
 
-    pub fn get_billing_pdf(&mut self, id: &str) -> Result<BillingPdf> {
+    pub fn get_billing_pdf(&mut self, id: &BillingId) -> Result<BillingPdf> {
         self.request_raw::<()>(
             Method::Get,
             &format!("/api/v1/billings/{}.pdf", id),
             None,
             None,
-        ).map(|res| res.map(BillingPdf))
+        ).map(|res| BillingPdf::new(res.body))
     }
 
     pub fn create_billing(&mut self, req: NewBilling) -> Result<Billing> {
@@ -141,29 +188,41 @@ impl Client {
         struct Request {
             billing: NewBilling,
         }
+        req.validate()?;
         self.post_json("/api/v1/billings", &Request { billing: req })
     }
 
-    pub fn update_billing(&mut self, id: &str, req: UpdateBilling) -> Result<Billing> {
+    pub fn update_billing(&mut self, id: &BillingId, req: UpdateBilling) -> Result<Billing> {
         #[derive(Serialize)]
         struct Request {
             billing: UpdateBilling,
         }
+        req.validate()?;
         self.patch_json(
             &format!("/api/v1/billings/{}", id),
             &Request { billing: req },
         )
     }
 
-    pub fn posting_billing(&mut self, id: &str) -> Result<()> {
+    /// 請求書を取得し、`options`の通りに変えた新しい請求書を作る
+    pub fn duplicate_billing(
+        &mut self,
+        id: &BillingId,
+        options: &DuplicateOptions,
+    ) -> Result<Billing> {
+        let billing = self.get_billing(id)?;
+        self.create_billing(options.apply(&billing)?)
+    }
+
+    pub fn posting_billing(&mut self, id: &BillingId) -> Result<()> {
         self.post_void(&format!("/api/v1/billings/{}/posting", id))
     }
 
-    pub fn cancel_posting_billing(&mut self, id: &str) -> Result<()> {
+    pub fn cancel_posting_billing(&mut self, id: &BillingId) -> Result<()> {
         self.post_void(&format!("/api/v1/billings/{}/cancel_posting", id))
     }
 
-    pub fn delete_billing(&mut self, id: &str) -> Result<()> {
+    pub fn delete_billing(&mut self, id: &BillingId) -> Result<()> {
         self.delete_void(&format!("/api/v1/billings/{}", id))
     }
 
@@ -171,68 +230,201 @@ impl Client {
         self.get("/api/v1/items.json")
     }
 
-    pub fn get_item(&mut self, id: &str) -> Result<Item> {
+    pub fn get_item(&mut self, id: &ItemId) -> Result<Item> {
         self.get(&format!("/api/v1/items/{}.json", id))
     }
     pub fn create_item(&mut self, req: NewItem) -> Result<Item> {
+        req.validate()?;
         self.post_json("/api/v1/items.json", &req)
     }
 
-    pub fn update_item(&mut self, id: &str, req: UpdateItem) -> Result<Item> {
+    pub fn update_item(&mut self, id: &ItemId, req: UpdateItem) -> Result<Item> {
+        req.validate()?;
         self.patch_json(&format!("/api/v1/items/{}", id), &req)
     }
 
-    pub fn delete_item(&mut self, id: &str) -> Result<()> {
+    pub fn delete_item(&mut self, id: &ItemId) -> Result<()> {
         self.delete_void(&format!("/api/v1/items/{}", id))
     }
 
     pub fn sent_history(&mut self, page: u32, per_page: u32) -> Result<SentHistories> {
+        validate::page(page, per_page)?;
+        let (page, per_page) = (page.to_string(), per_page.to_string());
         self.get_params(
             "/api/v1/sent_history.json",
-            &[
-                ("page", &page.to_string()),
-                ("per_page", &per_page.to_string()),
-            ],
+            &page_params(&page, &per_page),
         )
     }
 }
 
+/// 一覧APIを全ページ辿る
+impl Client {
+    /// 全ての取引先
+    pub fn partners(&mut self) -> Pager<Partners> {
+        Pager::new(self, |client, page, per_page| {
+            client.list_partners(page, per_page)
+        })
+    }
+
+    /// 全ての請求書
+    pub fn billings(&mut self) -> Pager<Billings> {
+        Pager::new(self, |client, page, per_page| {
+            client.list_billings(page, per_page)
+        })
+    }
+
+    /// 条件に合う全ての請求書
+    pub fn billings_search(&mut self, search: BillingSearch) -> Pager<BillingQueryResponse> {
+        Pager::new(self, move |client, page, per_page| {
+            client.search_billings(page, per_page, &search)
+        })
+    }
+
+    /// 全ての送付履歴
+    pub fn sent_histories(&mut self) -> Pager<SentHistories> {
+        Pager::new(self, |client, page, per_page| {
+            client.sent_history(page, per_page)
+        })
+    }
+}
+
+// 以下は同期版と非同期版のクライアントで共有する
+
+fn page_params<'a>(page: &'a str, per_page: &'a str) -> [(&'static str, &'a str); 2] {
+    [("page", page), ("per_page", per_page)]
+}
+
+fn borrow_params<'a>(params: &'a [(&'static str, String)]) -> Vec<(&'static str, &'a str)> {
+    params
+        .iter()
+        .map(|&(name, ref value)| (name, value.as_str()))
+        .collect()
+}
+
+fn is_unauthorized(error: &Error) -> bool {
+    error.status() == Some(reqwest::StatusCode::Unauthorized)
+}
+
+fn build_url(server: &Url, path: &str, params: Option<&[(&str, &str)]>) -> Result<Url> {
+    let mut url = server.join(path)?;
+    if let Some(params) = params {
+        url.query_pairs_mut().extend_pairs(params);
+    }
+    Ok(url)
+}
+
+fn decode_json<Res>(body: &[u8]) -> Result<Res>
+where
+    Res: DeserializeOwned,
+{
+    let body = String::from_utf8_lossy(body);
+    debug!("response json: {}", body);
+    serde_json::from_str(&body).map_err(|error| {
+        Error::Decode {
+            error,
+            body: body.into_owned(),
+        }
+    })
+}
+
 
 impl Client {
+    /// リクエストを送り、成功したらレスポンスを返す
     fn request_raw<Req>(
         &self,
         method: Method,
         path: &str,
         params: Option<&[(&str, &str)]>,
         data: Option<&Req>,
-    ) -> Result<reqwest::Response>
+    ) -> Result<HttpResponse>
     where
         Req: Serialize,
     {
-        use reqwest::header::{Authorization, Bearer};
+        let url = build_url(&self.server, path, params)?;
+        let body = match data {
+            Some(data) => Some(serde_json::to_vec(data).map_err(Error::Encode)?),
+            None => None,
+        };
+
+        let token = self.credentials.access_token();
+        match self.send_with_retry(&method, &url, &body) {
+            Err(ref e) if is_unauthorized(e) && self.credentials.refresh_params().is_some() => {
+                warn!("access token was rejected: {}", e);
+            }
+            res => return res,
+        }
+        // トークンを更新して1回だけ送り直す
+        self.credentials.refresh(&token, &*self.transport)?;
+        self.send_with_retry(&method, &url, &body)
+    }
+
+    fn send_with_retry(
+        &self,
+        method: &Method,
+        url: &Url,
+        body: &Option<Vec<u8>>,
+    ) -> Result<HttpResponse> {
+        let mut attempt = 1;
+        loop {
+            let failure = match self.send_once(method.clone(), url.clone(), body.clone()) {
+                Ok(res) => return Ok(res),
+                Err(failure) => failure,
+            };
+            match self.retry.delay(method, attempt, &failure) {
+                Some(delay) => {
+                    warn!(
+                        "retrying {} {} in {:?}: {}",
+                        method,
+                        url,
+                        delay,
+                        failure.error
+                    );
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                None => return Err(failure.error),
+            }
+        }
+    }
 
-        let mut url = self.server.join(path).unwrap();
-        if let Some(params) = params {
-            url.query_pairs_mut().extend_pairs(params);
+    fn send_once(
+        &self,
+        method: Method,
+        url: Url,
+        body: Option<Vec<u8>>,
+    ) -> ::std::result::Result<HttpResponse, Failure> {
+        use reqwest::header::{Authorization, Bearer, ContentType};
+
+        if let Some(ref limiter) = self.rate_limiter {
+            let wait = limiter.reserve();
+            if wait > Duration::new(0, 0) {
+                debug!("rate limited: waiting {:?}", wait);
+                thread::sleep(wait);
+            }
         }
 
-        let mut reqbuilder = self.client.request(method, url)?;
-        reqbuilder.header(Authorization(Bearer { token: self.token.clone() }));
-        if let Some(data) = data {
-            reqbuilder.json(data)?;
+        let mut headers = self.headers.clone();
+        headers.set(Authorization(Bearer { token: self.credentials.access_token() }));
+        if body.is_some() {
+            headers.set(ContentType::json());
         }
 
-        debug!("request: {:?}", reqbuilder);
-        let mut res = reqbuilder.send()?;
-        debug!("response: {:?}", res);
-        if res.status().is_success() {
-            Ok(Ok(res))
+        debug!("request: {} {}", method, url);
+        let res = self.transport.send(HttpRequest {
+            method,
+            url,
+            headers,
+            body,
+        })?;
+        debug!("response: {} {:?}", res.status, res.headers);
+        if res.status.is_success() {
+            Ok(res)
         } else {
-            Ok(Err(res.json()?))
+            let error = error::from_response(res.status, &res.body);
+            Err(Failure::new(error, Some(&res.headers)))
         }
     }
 
-
     fn request<Req, Res>(
         &self,
         method: Method,
@@ -244,17 +436,8 @@ impl Client {
         Req: Serialize,
         Res: DeserializeOwned,
     {
-        match self.request_raw(method, path, params, data) {
-            Ok(Ok(mut res)) => {
-                use std::io::Read;
-                let mut s = String::new();
-                res.read_to_string(&mut s).unwrap();
-                debug!("response json: {}", s);
-                Ok(Ok(serde_json::from_str(&s).unwrap()))
-            }
-            Ok(Err(e)) => Ok(Err(e)),
-            Err(e) => Err(e),
-        }
+        let res = self.request_raw(method, path, params, data)?;
+        decode_json(&res.body)
     }
 
     fn get<Res>(&mut self, path: &str) -> Result<Res>
@@ -280,9 +463,7 @@ impl Client {
     }
 
     fn post_void(&mut self, path: &str) -> Result<()> {
-        self.request_raw::<()>(Method::Post, path, None, None).map(
-            |res| res.map(|_| ()),
-        )
+        self.request_raw::<()>(Method::Post, path, None, None).map(|_| ())
     }
 
     fn patch_json<Req, Res>(&mut self, path: &str, req: &Req) -> Result<Res>
@@ -294,7 +475,6 @@ impl Client {
     }
 
     fn delete_void(&mut self, path: &str) -> Result<()> {
-        self.request_raw::<()>(Method::Delete, path, None, None)
-            .map(|res| res.map(|_| ()))
+        self.request_raw::<()>(Method::Delete, path, None, None).map(|_| ())
     }
 }
@@ -1,5 +1,14 @@
 use chrono::*;
-use reqwest;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+use std::io;
+
+use id::*;
+use invoice::RegistrationNumber;
+use money::Yen;
+use patch::Patch;
+use tax::TaxCategory;
 
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
 #[derive(Serialize, Deserialize)]
@@ -36,6 +45,9 @@ pub struct Office {
     pub tel: String,
     /// FAX番号 e.g. "03-5678-1234"
     pub fax: String,
+    /// 適格請求書発行事業者の登録番号 e.g. "T1234567890123"
+    #[serde(default, deserialize_with = "::invoice::deserialize_optional")]
+    pub registration_number: Option<RegistrationNumber>,
 }
 
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
@@ -43,19 +55,29 @@ pub struct Office {
 /// 事業所の更新リクエスト用データ
 pub struct UpdateOffice {
     /// 事業所名 e.g. "サンプル事業所"
-    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub name: Patch<String>,
     /// 郵便番号 e.g. "123-4567"
-    pub zip: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub zip: Patch<String>,
     /// 県 e.g. "東京都"
-    pub prefecture: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub prefecture: Patch<String>,
     /// 住所（丁目まで） e.g. "港区サンプル1-2-3"
-    pub address1: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub address1: Patch<String>,
     /// 住所（建物以降） e.g. "サンプルビル"
-    pub address2: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub address2: Patch<String>,
     /// 電話番号 e.g. "03-1234-5678"
-    pub tel: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tel: Patch<String>,
     /// FAX番号 e.g. "03-5678-1234"
-    pub fax: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub fax: Patch<String>,
+    /// 適格請求書発行事業者の登録番号 e.g. "T1234567890123"
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub registration_number: Patch<RegistrationNumber>,
 }
 
 
@@ -90,7 +112,7 @@ pub struct Meta {
 /// 取引先
 pub struct Partner {
     /// 取引先ID
-    pub id: String,
+    pub id: PartnerId,
     /// 顧客コード
     pub code: Option<String>,
     /// 名前
@@ -114,7 +136,7 @@ pub struct Partner {
 /// 部門
 pub struct Department {
     /// 部門ID
-    pub id: String,
+    pub id: DepartmentId,
     /// 郵便番号
     pub zip: Option<String>,
     /// 電話番号
@@ -178,15 +200,20 @@ pub struct NewPartner {
 /// 取引先更新用リクエストデータ
 pub struct UpdatePartner {
     /// 顧客コード
-    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub code: Patch<String>,
     /// 名前
-    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub name: Patch<String>,
     /// 名前 (カナ)
-    pub name_kana: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub name_kana: Patch<String>,
     /// 敬称
-    pub name_suffix: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub name_suffix: Patch<String>,
     /// メモ
-    pub memo: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub memo: Patch<String>,
     /// 部門
     pub departments: Vec<UpdateDepartmentInfo>,
 }
@@ -196,27 +223,37 @@ pub struct UpdatePartner {
 /// 取引先更新用リクエストデータに付随する部門更新用リクエストデータ
 pub struct UpdateDepartmentInfo {
     /// 部門ID。既存の部門を更新する際には必須です。
-    pub id: Option<String>,
+    pub id: Option<DepartmentId>,
     /// 郵便番号
-    pub zip: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub zip: Patch<String>,
     /// 電話番号
-    pub tel: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tel: Patch<String>,
     /// 都道府県
-    pub prefecture: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub prefecture: Patch<String>,
     /// 住所1
-    pub address1: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub address1: Patch<String>,
     /// 住所2
-    pub address2: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub address2: Patch<String>,
     /// 担当者氏名
-    pub person_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub person_name: Patch<String>,
     /// 担当者役職
-    pub person_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub person_title: Patch<String>,
     /// 部門名
-    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub name: Patch<String>,
     /// メールアドレス
-    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub email: Patch<String>,
     /// ccメールアドレス
-    pub cc_emails: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub cc_emails: Patch<String>,
 }
 
 
@@ -234,11 +271,11 @@ pub struct Billings {
 #[derive(Serialize, Deserialize)]
 pub struct Billing {
     /// 請求書ID e.g. "ABCDEFGHIJKLMNOPQRST123"
-    pub id: String,
+    pub id: BillingId,
     /// 取引先ID e.g. "ABCDEFGHIJKLMNOPQRST789"
-    pub partner_id: String,
+    pub partner_id: PartnerId,
     /// 部門ID e.g. "ABCDEFGHIJKLMNOPQRST012",
-    pub department_id: String,
+    pub department_id: DepartmentId,
     /// 取引先名 e.g. "サンプル取引先"
     pub partner_name: String,
     /// 取引先敬称 e.g. "様"
@@ -246,25 +283,31 @@ pub struct Billing {
     /// 取引先詳細 e.g. "hogehoge"
     pub partner_detail: String,
     /// 担当者ID e.g. "ABCDEFGHIJKLMNOPQRST345"
-    pub member_id: String,
+    pub member_id: MemberId,
     /// 担当者名 e.g. "member_name"
     pub member_name: Option<String>,
     /// 事業所名 e.g. "サンプル事業所"
     pub office_name: String,
     /// 事業所詳細 e.g. ""
     pub office_detail: String,
+    /// 事業所の登録番号 e.g. "T1234567890123"
+    #[serde(default, deserialize_with = "::invoice::deserialize_optional")]
+    pub registration_number: Option<RegistrationNumber>,
     /// 件名 e.g. "件名サンプル"
     pub title: Option<String>,
     /// 消費税 e.g. 80
-    pub excise_price: String,
+    pub excise_price: Yen,
     /// 小計額 e.g. 1000
-    pub subtotal: String,
+    pub subtotal: Yen,
     /// メモ e.g. ""
     pub memo: Option<String>,
     /// 支払条件 e.g. ""
     pub payment_condition: Option<String>,
     /// 合計額 e.g. 1080
-    pub total_price: String,
+    pub total_price: Yen,
+    /// 税区分ごとの小計と消費税。以前のAPIは返さない
+    #[serde(default)]
+    pub tax_summaries: Vec<TaxSummary>,
     /// 請求日
     pub billing_date: NaiveDate,
     /// 支払い期日
@@ -289,18 +332,171 @@ pub struct Billing {
     pub items: Vec<BillingItem>,
 }
 
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
+#[derive(Serialize, Deserialize)]
+/// 請求書の税区分ごとの内訳
+pub struct TaxSummary {
+    /// 税区分 e.g. "ten_percent"
+    pub excise: TaxCategory,
+    /// 税抜金額 e.g. 1000
+    pub subtotal: Yen,
+    /// 消費税 e.g. 100
+    pub excise_price: Yen,
+}
+
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
 #[derive(Serialize, Deserialize)]
 /// 請求書各種状況
 pub struct Status {
     /// 郵送状況 e.g. "未郵送"
-    pub posting: String,
+    pub posting: PostingStatus,
     /// メール状況 e.g. "未送信"
-    pub email: String,
+    pub email: EmailStatus,
     /// ダウンロード状況 e.g. ""
-    pub download: String,
+    pub download: DownloadStatus,
     /// 支払い 状況 e.g. "未設定"
-    pub payment: String,
+    pub payment: PaymentStatus,
+}
+
+/// APIが日本語の文字列で返す状態を列挙型にする
+///
+/// 知らない値は`Unknown`にそのまま入れるので、読んで書き戻しても値は変わりません。
+/// `Display`は日本語で、`{:#}`なら英語で表示します。
+macro_rules! status_enum {
+    (
+        $(#[$attr:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_attr:meta])* $variant:ident => ($ja:tt, $en:tt),)+
+        }
+        default $default:ident
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $($(#[$variant_attr])* $variant,)+
+            /// 知らない値
+            Unknown(String),
+        }
+
+        impl $name {
+            /// APIでの値
+            pub fn as_str(&self) -> &str {
+                match *self {
+                    $($name::$variant => $ja,)+
+                    $name::Unknown(ref value) => value,
+                }
+            }
+
+            /// 英語での表示
+            pub fn english(&self) -> &str {
+                match *self {
+                    $($name::$variant => $en,)+
+                    $name::Unknown(ref value) => value,
+                }
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::$default
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                match value.as_str() {
+                    $($ja => $name::$variant,)+
+                    _ => $name::Unknown(value),
+                }
+            }
+        }
+
+        impl<'a> From<&'a str> for $name {
+            fn from(value: &'a str) -> Self {
+                $name::from(value.to_string())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                if f.alternate() {
+                    f.write_str(self.english())
+                } else {
+                    f.write_str(self.as_str())
+                }
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                String::deserialize(deserializer).map($name::from)
+            }
+        }
+    };
+}
+
+status_enum! {
+    /// 郵送状況
+    pub enum PostingStatus {
+        /// 未郵送
+        NotPosted => ("未郵送", "not posted"),
+        /// 郵送待ち
+        Waiting => ("郵送待ち", "waiting for posting"),
+        /// 郵送済み
+        Posted => ("郵送済み", "posted"),
+        /// 郵送取消
+        Cancelled => ("郵送取消", "posting cancelled"),
+    }
+    default NotPosted
+}
+
+status_enum! {
+    /// メール状況
+    pub enum EmailStatus {
+        /// 未送信
+        NotSent => ("未送信", "not sent"),
+        /// 送信済み
+        Sent => ("送信済み", "sent"),
+    }
+    default NotSent
+}
+
+status_enum! {
+    /// ダウンロード状況
+    pub enum DownloadStatus {
+        /// ダウンロードされていない。APIは空文字列を返す
+        NotDownloaded => ("", "not downloaded"),
+        /// ダウンロード済み
+        Downloaded => ("ダウンロード済み", "downloaded"),
+    }
+    default NotDownloaded
+}
+
+status_enum! {
+    /// 支払い状況
+    pub enum PaymentStatus {
+        /// 未設定
+        Unset => ("未設定", "unset"),
+        /// 未入金
+        Unpaid => ("未入金", "unpaid"),
+        /// 入金済み
+        Paid => ("入金済み", "paid"),
+        /// 貸倒れ
+        BadDebt => ("貸倒れ", "bad debt"),
+    }
+    default Unset
 }
 
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
@@ -308,7 +504,7 @@ pub struct Status {
 /// 品目
 pub struct BillingItem {
     /// 品目ID e.g. "ABCDEFGHIJKLMNOPQRST012"
-    pub id: String,
+    pub id: BillingItemId,
     /// コード e.g. "ITEM-001"
     pub code: Option<String>,
     /// 品名 e.g. "商品A"
@@ -318,15 +514,15 @@ pub struct BillingItem {
     /// 数量 e.g. 1
     pub quantity: Option<u32>,
     /// 単価 e.g. 1000
-    pub unit_price: Option<u32>,
+    pub unit_price: Option<Yen>,
     /// 単位 e.g. "個"
     pub unit: Option<String>,
     /// 金額 e.g. 1000
-    pub price: Option<u32>,
+    pub price: Option<Yen>,
     /// 表示順 e.g. 0
     pub display_order: u32,
-    /// 課税対象 e.g. true
-    pub excise: bool,
+    /// 税区分 e.g. "ten_percent"
+    pub excise: TaxCategory,
     /// 作成日時 "2015/10/31T00:00:00.000+09:00"
     pub created_at: DateTime<FixedOffset>,
     /// 更新日時 "2015/10/31T00:00:00.000+09:00"
@@ -334,9 +530,22 @@ pub struct BillingItem {
 }
 
 #[derive(Debug)]
-pub struct BillingPdf(pub(crate) reqwest::Response);
-impl ::std::io::Read for BillingPdf {
-    fn read(&mut self, buf: &mut [u8]) -> ::std::io::Result<usize> {
+/// 請求書のPDF
+pub struct BillingPdf(io::Cursor<Vec<u8>>);
+
+impl BillingPdf {
+    pub(crate) fn new(body: Vec<u8>) -> Self {
+        BillingPdf(io::Cursor::new(body))
+    }
+
+    /// PDFの中身
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0.into_inner()
+    }
+}
+
+impl io::Read for BillingPdf {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.0.read(buf)
     }
 }
@@ -387,7 +596,7 @@ pub struct Condition {
 /// 請求書作成用リクエストデータ
 pub struct NewBilling {
     /// 部門ID
-    pub department_id: String,
+    pub department_id: DepartmentId,
     /// 件名
     pub title: Option<String>,
     /// 請求書番号
@@ -406,6 +615,8 @@ pub struct NewBilling {
     pub memo: Option<String>,
     /// 帳票名
     pub document_name: Option<String>,
+    /// 登録番号。省略すると事業所の登録番号
+    pub registration_number: Option<RegistrationNumber>,
     /// タグ。カンマ区切り文字列で記載
     pub tags: Option<String>,
     /// 品目
@@ -417,7 +628,7 @@ pub struct NewBilling {
 /// 請求書作成用リクエストデータに付随する品目データ
 pub struct NewBillingItem {
     /// 品目ID。IDがあれば既存の品目の更新を、IDがなければ追加を意味する
-    pub id: Option<String>,
+    pub id: Option<ItemId>,
     /// 名前
     pub name: Option<String>,
     /// コード
@@ -427,39 +638,54 @@ pub struct NewBillingItem {
     /// 数量
     pub quantity: Option<String>,
     /// 単価
-    pub unit_price: Option<String>,
+    pub unit_price: Option<Yen>,
     /// 単位
     pub unit: Option<String>,
-    /// 税対象
-    pub excise: bool,
+    /// 税区分
+    #[serde(serialize_with = "::tax::serialize_excise")]
+    pub excise: TaxCategory,
 }
 
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
 #[derive(Serialize, Deserialize)]
 /// 請求書更新用リクエストデータ
 pub struct UpdateBilling {
-    /// 部門ID
-    pub department_id: String,
+    /// 部門ID。請求先を変える時だけ指定する
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub department_id: Patch<DepartmentId>,
     /// 件名
-    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub title: Patch<String>,
     /// 請求書番号
-    pub billing_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub billing_number: Patch<String>,
     /// 振込先
-    pub payment_condition: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub payment_condition: Patch<String>,
     /// 備考
-    pub note: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub note: Patch<String>,
     /// 請求日
-    pub billing_date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub billing_date: Patch<NaiveDate>,
     /// お支払期限
-    pub due_date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub due_date: Patch<NaiveDate>,
     /// 売上計上日
-    pub sales_date: Option<NaiveDate>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub sales_date: Patch<NaiveDate>,
     /// メモ
-    pub memo: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub memo: Patch<String>,
     /// 帳票名
-    pub document_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub document_name: Patch<String>,
+    /// 登録番号
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub registration_number: Patch<RegistrationNumber>,
     /// タグ。カンマ区切り文字列で記載
-    pub tags: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub tags: Patch<String>,
     /// 品目
     pub items: Vec<UpdateBillingItem>,
 }
@@ -469,21 +695,29 @@ pub struct UpdateBilling {
 /// 請求書更新用リクエストデータに付随する品目更新用データ
 pub struct UpdateBillingItem {
     /// 品目Id
-    pub id: Option<String>,
+    pub id: Option<BillingItemId>,
     /// 名前
-    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub name: Patch<String>,
     /// コード
-    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub code: Patch<String>,
     /// 詳細
-    pub detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub detail: Patch<String>,
     /// 数量
-    pub quantity: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub quantity: Patch<String>,
     /// 単価
-    pub unit_price: Option<String>,
-    /// 単位
-    pub unit: Option<u32>,
-    /// 税対象
-    pub excise: bool,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub unit_price: Patch<Yen>,
+    /// 単位 e.g. "個"
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub unit: Patch<String>,
+    /// 税区分
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged",
+            serialize_with = "::tax::serialize_excise_patch")]
+    pub excise: Patch<TaxCategory>,
     /// 削除するならtrue
     pub _destroy: bool,
 }
@@ -517,7 +751,7 @@ pub struct ItemsMeta {
 #[derive(Serialize, Deserialize)]
 pub struct Item {
     /// 品目ID e.g. "ABCDEFGHIJKLMNOPQRST012"
-    pub id: String,
+    pub id: ItemId,
     /// コード e.g. "ITEM-001"
     pub code: Option<String>,
     /// 名前 e.g. "商品A"
@@ -527,13 +761,13 @@ pub struct Item {
     /// 数量 e.g. 1
     pub quantity: Option<u32>,
     /// 単価 e.g. 1000
-    pub unit_price: Option<u32>,
+    pub unit_price: Option<Yen>,
     /// 単位 e.g. "個"
     pub unit: Option<String>,
     /// 金額 e.g. 1000
-    pub price: String,
-    /// 課税対象 e.g. true
-    pub excise: bool,
+    pub price: Yen,
+    /// 税区分 e.g. "ten_percent"
+    pub excise: TaxCategory,
     /// 作成日時 e.g. "2015/10/31T00:00:00.000+09:00"
     pub created_at: DateTime<FixedOffset>,
     /// 更新日時 e.g. "2015/10/31T00:00:00.000+09:00"
@@ -551,13 +785,14 @@ pub struct NewItem {
     /// 詳細
     pub detail: Option<String>,
     /// 単価
-    pub unit_price: Option<u32>,
+    pub unit_price: Option<Yen>,
     /// 単位
     pub unit: Option<String>,
     /// 数量
     pub quantity: Option<u32>,
-    /// 消費税を計算するか
-    pub excise: Option<bool>,
+    /// 税区分
+    #[serde(serialize_with = "::tax::serialize_optional_excise")]
+    pub excise: Option<TaxCategory>,
 }
 
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
@@ -565,19 +800,27 @@ pub struct NewItem {
 /// 品目更新用のリクエストデータ
 pub struct UpdateItem {
     /// 名前
-    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub name: Patch<String>,
     /// 品目コード
-    pub code: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub code: Patch<String>,
     /// 詳細
-    pub detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub detail: Patch<String>,
     /// 単価
-    pub unit_price: Option<u32>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub unit_price: Patch<Yen>,
     /// 単位
-    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub unit: Patch<String>,
     /// 数量
-    pub quantity: Option<u32>,
-    /// 消費税を計算するか
-    pub excise: Option<bool>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub quantity: Patch<u32>,
+    /// 税区分
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged",
+            serialize_with = "::tax::serialize_excise_patch")]
+    pub excise: Patch<TaxCategory>,
 }
 
 #[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
@@ -0,0 +1,345 @@
+//! 金額
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, Default)]
+/// 円単位の金額
+///
+/// APIは金額を数値で返すことも文字列（e.g. "1080", "1080.0"）で返すこともあるので、どちらからも読めます。
+/// 書き出す時は数値にします。
+///
+/// 演算子による計算は桁あふれするとビルドの設定によらずパニックします。
+/// 外から受け取った金額には`checked_add`などを使ってください。
+pub struct Yen(pub i64);
+
+impl Yen {
+    pub fn new(amount: i64) -> Self {
+        Yen(amount)
+    }
+
+    /// 円単位の値
+    pub fn amount(&self) -> i64 {
+        self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// 桁あふれしたら`None`
+    pub fn checked_add(self, other: Yen) -> Option<Yen> {
+        self.0.checked_add(other.0).map(Yen)
+    }
+
+    /// 桁あふれしたら`None`
+    pub fn checked_sub(self, other: Yen) -> Option<Yen> {
+        self.0.checked_sub(other.0).map(Yen)
+    }
+
+    /// 単価×数量。桁あふれしたら`None`
+    pub fn checked_mul(self, quantity: i64) -> Option<Yen> {
+        self.0.checked_mul(quantity).map(Yen)
+    }
+}
+
+/// 演算子の結果。桁あふれしたらパニックする
+fn expect(result: Option<Yen>) -> Yen {
+    result.expect("amount of yen overflowed")
+}
+
+impl From<i64> for Yen {
+    fn from(amount: i64) -> Self {
+        Yen(amount)
+    }
+}
+
+impl From<u32> for Yen {
+    fn from(amount: u32) -> Self {
+        Yen(i64::from(amount))
+    }
+}
+
+impl From<Yen> for i64 {
+    fn from(yen: Yen) -> Self {
+        yen.0
+    }
+}
+
+impl fmt::Display for Yen {
+    /// 3桁ごとにカンマを入れる e.g. "¥1,080"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.0.to_string();
+        let digits = digits.trim_left_matches('-');
+        let mut grouped = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                grouped.push(',');
+            }
+            grouped.push(c);
+        }
+        let sign = if self.0 < 0 { "-" } else { "" };
+        write!(f, "{}¥{}", sign, grouped)
+    }
+}
+
+impl FromStr for Yen {
+    type Err = ParseYenError;
+
+    /// "1080"、"1,080"、"1080.0"、"-¥1,080"などを読む。小数部が0でなければエラー
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseYenError(s.into());
+        let trimmed = s.trim();
+        // `Display`と同じく符号は記号の前にある
+        let (sign, unsigned) = if trimmed.starts_with('-') {
+            ("-", &trimmed[1..])
+        } else {
+            ("", trimmed)
+        };
+        let cleaned: String = unsigned
+            .trim_left_matches('¥')
+            .trim_right_matches('円')
+            .chars()
+            .filter(|&c| c != ',')
+            .collect();
+        let (integer, fraction) = match cleaned.find('.') {
+            Some(i) => (&cleaned[..i], &cleaned[i + 1..]),
+            None => (cleaned.as_str(), ""),
+        };
+        if integer.is_empty() || !integer.chars().all(|c| c.is_ascii_digit()) ||
+            !fraction.chars().all(|c| c == '0')
+        {
+            return Err(error());
+        }
+        format!("{}{}", sign, integer)
+            .parse()
+            .map(Yen)
+            .map_err(|_| error())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// 金額として読めない文字列だった
+pub struct ParseYenError(String);
+
+impl fmt::Display for ParseYenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid amount of yen: {:?}", self.0)
+    }
+}
+
+impl StdError for ParseYenError {
+    fn description(&self) -> &str {
+        "invalid amount of yen"
+    }
+}
+
+impl Add for Yen {
+    type Output = Yen;
+
+    fn add(self, other: Yen) -> Yen {
+        expect(self.checked_add(other))
+    }
+}
+
+impl AddAssign for Yen {
+    fn add_assign(&mut self, other: Yen) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Yen {
+    type Output = Yen;
+
+    fn sub(self, other: Yen) -> Yen {
+        expect(self.checked_sub(other))
+    }
+}
+
+impl SubAssign for Yen {
+    fn sub_assign(&mut self, other: Yen) {
+        *self = *self - other;
+    }
+}
+
+impl Neg for Yen {
+    type Output = Yen;
+
+    fn neg(self) -> Yen {
+        expect(self.0.checked_neg().map(Yen))
+    }
+}
+
+/// 単価×数量
+impl Mul<i64> for Yen {
+    type Output = Yen;
+
+    fn mul(self, quantity: i64) -> Yen {
+        expect(self.checked_mul(quantity))
+    }
+}
+
+impl Mul<u32> for Yen {
+    type Output = Yen;
+
+    fn mul(self, quantity: u32) -> Yen {
+        self * i64::from(quantity)
+    }
+}
+
+impl Sum for Yen {
+    fn sum<I: Iterator<Item = Yen>>(iter: I) -> Yen {
+        iter.fold(Yen(0), Add::add)
+    }
+}
+
+impl<'a> Sum<&'a Yen> for Yen {
+    fn sum<I: Iterator<Item = &'a Yen>>(iter: I) -> Yen {
+        iter.cloned().sum()
+    }
+}
+
+impl Serialize for Yen {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Yen {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(YenVisitor)
+    }
+}
+
+struct YenVisitor;
+
+impl<'de> Visitor<'de> for YenVisitor {
+    type Value = Yen;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an amount of yen as a number or a string")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Yen, E> {
+        Ok(Yen(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Yen, E> {
+        if v > i64::max_value() as u64 {
+            return Err(E::invalid_value(de::Unexpected::Unsigned(v), &self));
+        }
+        Ok(Yen(v as i64))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Yen, E> {
+        // `i64::max_value() as f64`は2^63に丸められるので、それ未満でなければならない
+        let in_range = i64::min_value() as f64 <= v && v < i64::max_value() as f64;
+        if !in_range || v.fract() != 0.0 {
+            return Err(E::invalid_value(de::Unexpected::Float(v), &self));
+        }
+        Ok(Yen(v as i64))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Yen, E> {
+        v.parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn read(json: &str) -> serde_json::Result<Yen> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn reads_numbers() {
+        assert_eq!(read("1080").unwrap(), Yen(1080));
+        assert_eq!(read("-1080").unwrap(), Yen(-1080));
+        assert_eq!(read("9223372036854775807").unwrap(), Yen(i64::max_value()));
+        assert!(read("9223372036854775808").is_err());
+    }
+
+    #[test]
+    fn reads_floats_without_fractions() {
+        assert_eq!(read("1080.0").unwrap(), Yen(1080));
+        assert_eq!(read("-1080.0").unwrap(), Yen(-1080));
+        assert_eq!(read("-9223372036854775808.0").unwrap(), Yen(i64::min_value()));
+        assert!(read("1080.5").is_err());
+        // 2^63
+        assert!(read("9223372036854775808.0").is_err());
+        assert!(read("1e300").is_err());
+    }
+
+    #[test]
+    fn reads_strings() {
+        assert_eq!(read(r#""1080""#).unwrap(), Yen(1080));
+        assert_eq!(read(r#""1,080.00""#).unwrap(), Yen(1080));
+        assert_eq!(read(r#""¥1,080""#).unwrap(), Yen(1080));
+        assert!(read(r#""1080.5""#).is_err());
+        assert!(read(r#""円""#).is_err());
+    }
+
+    #[test]
+    fn parses_what_it_displays() {
+        for &amount in &[0, 1, 999, 1000, 1080, -1080, 1234567, i64::max_value(), i64::min_value()] {
+            let yen = Yen(amount);
+            assert_eq!(yen.to_string().parse::<Yen>(), Ok(yen));
+        }
+        assert_eq!(Yen(1234567).to_string(), "¥1,234,567");
+        assert_eq!(Yen(-1080).to_string(), "-¥1,080");
+        assert_eq!(Yen(0).to_string(), "¥0");
+    }
+
+    #[test]
+    fn parses_yen_notations() {
+        assert_eq!("1080".parse(), Ok(Yen(1080)));
+        assert_eq!(" 1,080円 ".parse(), Ok(Yen(1080)));
+        assert_eq!("-1,080円".parse(), Ok(Yen(-1080)));
+        assert_eq!("-¥1,080".parse(), Ok(Yen(-1080)));
+        assert!("".parse::<Yen>().is_err());
+        assert!("¥".parse::<Yen>().is_err());
+        assert!("--1080".parse::<Yen>().is_err());
+        assert!("¥-1080".parse::<Yen>().is_err());
+        assert!("+1080".parse::<Yen>().is_err());
+        assert!("10.80".parse::<Yen>().is_err());
+        assert!("9223372036854775808".parse::<Yen>().is_err());
+    }
+
+    #[test]
+    fn writes_numbers() {
+        assert_eq!(serde_json::to_string(&Yen(-1080)).unwrap(), "-1080");
+    }
+
+    #[test]
+    fn checked_arithmetic_reports_overflow() {
+        assert_eq!(Yen(1).checked_add(Yen(2)), Some(Yen(3)));
+        assert_eq!(Yen(i64::max_value()).checked_add(Yen(1)), None);
+        assert_eq!(Yen(i64::min_value()).checked_sub(Yen(1)), None);
+        assert_eq!(Yen(1000).checked_mul(3), Some(Yen(3000)));
+        assert_eq!(Yen(i64::max_value()).checked_mul(2), None);
+        assert_eq!(Yen(1000) * 3u32 - Yen(500), Yen(2500));
+        assert_eq!(vec![Yen(1), Yen(2)].iter().sum::<Yen>(), Yen(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "amount of yen overflowed")]
+    fn operators_panic_on_overflow() {
+        let _ = vec![Yen(i64::max_value()), Yen(1)].into_iter().sum::<Yen>();
+    }
+}
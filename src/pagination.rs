@@ -0,0 +1,404 @@
+//! ページ分割された一覧を順に辿る
+//!
+//! ```ignore
+//! for partner in client.partners().iter() {
+//!     println!("{}", partner?.name);
+//! }
+//! ```
+
+use std::vec;
+
+#[cfg(feature = "async")]
+use futures::{stream, Future, Stream};
+
+use model::*;
+use {Client, Result};
+#[cfg(feature = "async")]
+use {AsyncClient, Error};
+#[cfg(feature = "async")]
+use async_client::ApiFuture;
+
+/// 1ページあたりの項目数の上限
+pub const MAX_PER_PAGE: u32 = 100;
+
+/// 1ページあたりの項目数のデフォルト。APIの上限と同じ
+pub const DEFAULT_PER_PAGE: u32 = MAX_PER_PAGE;
+
+/// ページ分割された一覧の1ページ
+pub trait Page {
+    type Item;
+
+    /// 総ページ数
+    fn total_pages(&self) -> u32;
+
+    /// ページ内の項目
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+impl Page for Partners {
+    type Item = Partner;
+
+    fn total_pages(&self) -> u32 {
+        self.meta.total_pages
+    }
+
+    fn into_items(self) -> Vec<Partner> {
+        self.partners
+    }
+}
+
+impl Page for Billings {
+    type Item = Billing;
+
+    fn total_pages(&self) -> u32 {
+        self.meta.total_pages
+    }
+
+    fn into_items(self) -> Vec<Billing> {
+        self.billings
+    }
+}
+
+impl Page for BillingQueryResponse {
+    type Item = Billing;
+
+    fn total_pages(&self) -> u32 {
+        self.meta.total_pages
+    }
+
+    fn into_items(self) -> Vec<Billing> {
+        self.billings
+    }
+}
+
+impl Page for SentHistories {
+    type Item = SentHistory;
+
+    fn total_pages(&self) -> u32 {
+        self.meta.total_pages
+    }
+
+    fn into_items(self) -> Vec<SentHistory> {
+        self.sent_history_list
+    }
+}
+
+/// 一覧を必要になった時に1ページずつ取得する
+pub struct Pager<'a, P> {
+    client: &'a mut Client,
+    fetch: Box<FnMut(&mut Client, u32, u32) -> Result<P> + 'a>,
+    per_page: u32,
+    start_page: u32,
+}
+
+impl<'a, P: Page> Pager<'a, P> {
+    pub(crate) fn new<F>(client: &'a mut Client, fetch: F) -> Self
+    where
+        F: FnMut(&mut Client, u32, u32) -> Result<P> + 'a,
+    {
+        Self {
+            client,
+            fetch: Box::new(fetch),
+            per_page: DEFAULT_PER_PAGE,
+            start_page: 1,
+        }
+    }
+
+    /// 1ページあたりの項目数
+    pub fn per_page(mut self, per_page: u32) -> Self {
+        self.per_page = per_page;
+        self
+    }
+
+    /// 最初に取得するページ
+    pub fn start_page(mut self, start_page: u32) -> Self {
+        self.start_page = start_page;
+        self
+    }
+
+    /// ページ毎に返すイテレータ
+    pub fn pages(self) -> Pages<'a, P> {
+        Pages {
+            next_page: self.start_page,
+            pager: self,
+            total_pages: None,
+            done: false,
+        }
+    }
+
+    /// 項目を1つずつ返すイテレータ
+    pub fn iter(self) -> PageItems<'a, P> {
+        PageItems {
+            pages: self.pages(),
+            current: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<'a, P: Page> IntoIterator for Pager<'a, P> {
+    type Item = Result<P::Item>;
+    type IntoIter = PageItems<'a, P>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// ページ毎のイテレータ。エラーが起きたらそれを返して終わる
+pub struct Pages<'a, P> {
+    pager: Pager<'a, P>,
+    next_page: u32,
+    total_pages: Option<u32>,
+    done: bool,
+}
+
+impl<'a, P: Page> Iterator for Pages<'a, P> {
+    type Item = Result<P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(total_pages) = self.total_pages {
+            if self.next_page > total_pages {
+                self.done = true;
+                return None;
+            }
+        }
+        let pager = &mut self.pager;
+        match (pager.fetch)(&mut *pager.client, self.next_page, pager.per_page) {
+            Ok(page) => {
+                self.total_pages = Some(page.total_pages());
+                self.next_page += 1;
+                Some(Ok(page))
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// 項目毎のイテレータ。エラーが起きたらそれを返して終わる
+pub struct PageItems<'a, P: Page> {
+    pages: Pages<'a, P>,
+    current: vec::IntoIter<P::Item>,
+}
+
+impl<'a, P: Page> Iterator for PageItems<'a, P> {
+    type Item = Result<P::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.current.next() {
+                return Some(Ok(item));
+            }
+            match self.pages.next() {
+                Some(Ok(page)) => self.current = page.into_items().into_iter(),
+                Some(Err(e)) => return Some(Err(e)),
+                None => return None,
+            }
+        }
+    }
+}
+
+/// 非同期版の`Pager`
+#[cfg(feature = "async")]
+pub struct AsyncPager<P> {
+    client: AsyncClient,
+    fetch: Box<Fn(&AsyncClient, u32, u32) -> ApiFuture<P>>,
+    per_page: u32,
+    start_page: u32,
+}
+
+#[cfg(feature = "async")]
+impl<P> AsyncPager<P>
+where
+    P: Page + 'static,
+    P::Item: 'static,
+{
+    pub(crate) fn new<F>(client: AsyncClient, fetch: F) -> Self
+    where
+        F: Fn(&AsyncClient, u32, u32) -> ApiFuture<P> + 'static,
+    {
+        Self {
+            client,
+            fetch: Box::new(fetch),
+            per_page: DEFAULT_PER_PAGE,
+            start_page: 1,
+        }
+    }
+
+    /// 1ページあたりの項目数
+    pub fn per_page(mut self, per_page: u32) -> Self {
+        self.per_page = per_page;
+        self
+    }
+
+    /// 最初に取得するページ
+    pub fn start_page(mut self, start_page: u32) -> Self {
+        self.start_page = start_page;
+        self
+    }
+
+    /// ページ毎に返すストリーム。エラーが起きたらそれを返して終わる
+    pub fn pages(self) -> Box<Stream<Item = P, Error = Error>> {
+        let AsyncPager {
+            client,
+            fetch,
+            per_page,
+            start_page,
+        } = self;
+        Box::new(stream::unfold(
+            (start_page, None),
+            move |(next_page, total_pages)| {
+                if total_pages.map_or(false, |total_pages| next_page > total_pages) {
+                    return None;
+                }
+                Some(fetch(&client, next_page, per_page).map(move |page| {
+                    let total_pages = page.total_pages();
+                    (page, (next_page + 1, Some(total_pages)))
+                }))
+            },
+        ))
+    }
+
+    /// 項目を1つずつ返すストリーム。エラーが起きたらそれを返して終わる
+    pub fn stream(self) -> Box<Stream<Item = P::Item, Error = Error>> {
+        Box::new(
+            self.pages()
+                .map(|page| stream::iter_ok::<_, Error>(page.into_items()))
+                .flatten(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use transport::{HttpRequest, HttpResponse, Transport};
+    use {ClientBuilder, Error};
+
+    /// ページは取得関数が返すので、実際には使われない
+    struct Offline;
+
+    impl Transport for Offline {
+        fn send(&self, _: HttpRequest) -> Result<HttpResponse> {
+            Err(Error::Timeout)
+        }
+    }
+
+    struct Numbers {
+        total_pages: u32,
+        items: Vec<u32>,
+    }
+
+    impl Page for Numbers {
+        type Item = u32;
+
+        fn total_pages(&self) -> u32 {
+            self.total_pages
+        }
+
+        fn into_items(self) -> Vec<u32> {
+            self.items
+        }
+    }
+
+    fn client() -> Client {
+        ClientBuilder::new()
+            .transport(Offline)
+            .build("access-token")
+            .unwrap()
+    }
+
+    /// `pages`の各ページを返し、要求された(page, per_page)を`calls`に記録する
+    fn pager<'a>(
+        client: &'a mut Client,
+        pages: Vec<Result<Vec<u32>>>,
+        calls: &'a RefCell<Vec<(u32, u32)>>,
+    ) -> Pager<'a, Numbers> {
+        let total_pages = pages.len() as u32;
+        let mut pages = pages.into_iter();
+        Pager::new(client, move |_, page, per_page| {
+            calls.borrow_mut().push((page, per_page));
+            let items = pages.next().expect("fetched past the last page")?;
+            Ok(Numbers { total_pages, items })
+        })
+    }
+
+    fn collect(items: PageItems<Numbers>) -> Vec<::std::result::Result<u32, String>> {
+        items.map(|item| item.map_err(|e| e.to_string())).collect()
+    }
+
+    #[test]
+    fn stops_at_total_pages() {
+        let mut client = client();
+        let calls = RefCell::new(Vec::new());
+        let pages = vec![Ok(vec![1, 2]), Ok(vec![3, 4]), Ok(vec![5])];
+        let items = pager(&mut client, pages, &calls).per_page(2).iter();
+        assert_eq!(collect(items), vec![Ok(1), Ok(2), Ok(3), Ok(4), Ok(5)]);
+        assert_eq!(*calls.borrow(), vec![(1, 2), (2, 2), (3, 2)]);
+    }
+
+    #[test]
+    fn starts_at_the_given_page() {
+        let mut client = client();
+        let calls = RefCell::new(Vec::new());
+        let pages = vec![Ok(vec![3]), Ok(vec![4]), Ok(vec![])];
+        let pages = pager(&mut client, pages, &calls)
+            .start_page(2)
+            .pages()
+            .map(|page| page.unwrap().items)
+            .collect::<Vec<_>>();
+        // 総ページ数は3なので、2ページ目から2ページ分だけ取得する
+        assert_eq!(pages, vec![vec![3], vec![4]]);
+        assert_eq!(
+            *calls.borrow(),
+            vec![(2, DEFAULT_PER_PAGE), (3, DEFAULT_PER_PAGE)]
+        );
+    }
+
+    #[test]
+    fn stops_after_an_error() {
+        let mut client = client();
+        let calls = RefCell::new(Vec::new());
+        let pages = vec![Ok(vec![1]), Err(Error::Timeout), Ok(vec![3])];
+        let mut items = pager(&mut client, pages, &calls).iter();
+        assert_eq!(items.next().unwrap().unwrap(), 1);
+        match items.next() {
+            Some(Err(Error::Timeout)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(items.next().is_none());
+        assert!(items.next().is_none());
+        assert_eq!(calls.borrow().len(), 2);
+    }
+
+    #[test]
+    fn handles_an_empty_first_page() {
+        let mut client = client();
+        let calls = RefCell::new(Vec::new());
+        let pager = Pager::new(&mut client, |_, page, per_page| {
+            calls.borrow_mut().push((page, per_page));
+            Ok(Numbers {
+                total_pages: 0,
+                items: Vec::new(),
+            })
+        });
+        assert_eq!(collect(pager.iter()), vec![]);
+        assert_eq!(calls.borrow().len(), 1);
+    }
+
+    #[test]
+    fn skips_empty_pages_in_the_middle() {
+        let mut client = client();
+        let calls = RefCell::new(Vec::new());
+        let pages = vec![Ok(vec![1]), Ok(vec![]), Ok(vec![3])];
+        let items = pager(&mut client, pages, &calls).iter();
+        assert_eq!(collect(items), vec![Ok(1), Ok(3)]);
+    }
+}
@@ -0,0 +1,109 @@
+//! 更新リクエストのフィールド
+//!
+//! 更新APIでは「変更しない」と「空にする」を区別する必要があります。
+//! `Patch::Unchanged`のフィールドはJSONに含めず、`Patch::Clear`は`null`、`Patch::Set`は値として送ります。
+//!
+//! ```ignore
+//! let update = UpdateBilling {
+//!     memo: Patch::Set("更新しました".into()),
+//!     note: Patch::Clear,
+//!     ..Default::default()
+//! };
+//! ```
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// 更新リクエストの1フィールド
+///
+/// 構造体のフィールドには`#[serde(default, skip_serializing_if = "Patch::is_unchanged")]`を付けて下さい。
+pub enum Patch<T> {
+    /// 変更しない。送らない
+    Unchanged,
+    /// 空にする。`null`を送る
+    Clear,
+    /// 値を設定する
+    Set(T),
+}
+
+impl<T> Default for Patch<T> {
+    fn default() -> Self {
+        Patch::Unchanged
+    }
+}
+
+/// `None`は`Patch::Clear`になる
+impl<T> From<Option<T>> for Patch<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => Patch::Set(value),
+            None => Patch::Clear,
+        }
+    }
+}
+
+impl<T> Patch<T> {
+    pub fn is_unchanged(&self) -> bool {
+        match *self {
+            Patch::Unchanged => true,
+            _ => false,
+        }
+    }
+
+    /// 設定する値。変更しない場合と空にする場合は`None`
+    pub fn value(&self) -> Option<&T> {
+        match *self {
+            Patch::Set(ref value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// 設定する値。変更しない場合と空にする場合は`None`
+    pub fn into_value(self) -> Option<T> {
+        match self {
+            Patch::Set(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Patch<U> {
+        match self {
+            Patch::Unchanged => Patch::Unchanged,
+            Patch::Clear => Patch::Clear,
+            Patch::Set(value) => Patch::Set(f(value)),
+        }
+    }
+
+    /// `field`に反映する
+    pub fn apply(self, field: &mut Option<T>) {
+        match self {
+            Patch::Unchanged => {}
+            Patch::Clear => *field = None,
+            Patch::Set(value) => *field = Some(value),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for Patch<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            Patch::Set(ref value) => serializer.serialize_some(value),
+            // `Unchanged`は`skip_serializing_if`で飛ばされる前提
+            Patch::Unchanged | Patch::Clear => serializer.serialize_none(),
+        }
+    }
+}
+
+/// `null`は`Patch::Clear`として読む。フィールドがなければ`#[serde(default)]`で`Patch::Unchanged`になる
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::deserialize(deserializer).map(Patch::from)
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// クライアント側で守るリクエスト数の上限
+///
+/// `period`あたり`requests`回まで送ります。上限に達したリクエストは送れるようになるまで待ちます。
+pub struct RateLimit {
+    /// 期間あたりのリクエスト数
+    pub requests: u32,
+    /// 期間
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub fn new(requests: u32, period: Duration) -> Self {
+        assert!(requests > 0, "rate limit must allow at least one request");
+        assert!(period > Duration::new(0, 0), "rate limit period must not be zero");
+        Self { requests, period }
+    }
+
+    /// 1秒あたり`requests`回
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// 1分あたり`requests`回
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+}
+
+/// トークンバケットによるリミッタ。クローンしたものとバケットを共有する
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimiter {
+    buckets: Arc<Mutex<Vec<Bucket>>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    /// 1秒あたりに補充されるトークン数
+    rate: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(limits: &[RateLimit]) -> Self {
+        let now = Instant::now();
+        let buckets = limits
+            .iter()
+            .map(|limit| {
+                let capacity = f64::from(limit.requests);
+                Bucket {
+                    capacity,
+                    tokens: capacity,
+                    rate: capacity / secs(limit.period),
+                    last: now,
+                }
+            })
+            .collect();
+        Self { buckets: Arc::new(Mutex::new(buckets)) }
+    }
+
+    /// リクエスト1回分を予約し、送ってよくなるまでの待ち時間を返す
+    ///
+    /// 待っている間も予約は有効なので、並行して呼ばれても上限を超えない。
+    pub fn reserve(&self) -> Duration {
+        self.reserve_at(Instant::now())
+    }
+
+    /// 時刻`now`にリクエスト1回分を予約する
+    fn reserve_at(&self, now: Instant) -> Duration {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let mut wait: f64 = 0.0;
+        for bucket in buckets.iter_mut() {
+            // 時計が戻った場合は補充しない
+            if now > bucket.last {
+                let elapsed = secs(now.duration_since(bucket.last));
+                bucket.tokens = (bucket.tokens + elapsed * bucket.rate).min(bucket.capacity);
+                bucket.last = now;
+            }
+            bucket.tokens -= 1.0;
+            if bucket.tokens < 0.0 {
+                wait = wait.max(-bucket.tokens / bucket.rate);
+            }
+        }
+        let nanos = (wait * 1e9) as u64;
+        Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32)
+    }
+}
+
+fn secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 丸め誤差を許して待ち時間を比べる
+    fn assert_wait(actual: Duration, millis: u64) {
+        let expected = Duration::from_millis(millis);
+        let diff = if actual > expected {
+            actual - expected
+        } else {
+            expected - actual
+        };
+        assert!(
+            diff < Duration::from_millis(1),
+            "waited {:?}, expected {:?}",
+            actual,
+            expected
+        );
+    }
+
+    fn after(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn allows_a_burst_up_to_the_limit() {
+        let limiter = RateLimiter::new(&[RateLimit::per_second(3)]);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(limiter.reserve_at(start), Duration::new(0, 0));
+        }
+        assert_wait(limiter.reserve_at(start), 333);
+        // 待っている間の予約も数える
+        assert_wait(limiter.reserve_at(start), 667);
+    }
+
+    #[test]
+    fn refills_over_time() {
+        let limiter = RateLimiter::new(&[RateLimit::per_second(2)]);
+        let start = Instant::now();
+        limiter.reserve_at(start);
+        limiter.reserve_at(start);
+        assert_wait(limiter.reserve_at(after(start, 250)), 250);
+        // 上の予約で使った分を補充し終えるのは1秒後
+        assert_eq!(limiter.reserve_at(after(start, 1000)), Duration::new(0, 0));
+        // 長く空いても容量までしか貯まらない
+        let later = after(start, 10_000);
+        assert_eq!(limiter.reserve_at(later), Duration::new(0, 0));
+        assert_eq!(limiter.reserve_at(later), Duration::new(0, 0));
+        assert_wait(limiter.reserve_at(later), 500);
+    }
+
+    #[test]
+    fn waits_for_the_strictest_limit() {
+        let limiter = RateLimiter::new(&[RateLimit::per_second(2), RateLimit::per_minute(3)]);
+        let start = Instant::now();
+        assert_eq!(limiter.reserve_at(start), Duration::new(0, 0));
+        assert_eq!(limiter.reserve_at(start), Duration::new(0, 0));
+        assert_wait(limiter.reserve_at(start), 500);
+        assert_wait(limiter.reserve_at(after(start, 2000)), 18_000);
+    }
+
+    #[test]
+    fn shares_buckets_between_clones() {
+        let limiter = RateLimiter::new(&[RateLimit::per_second(1)]);
+        let clone = limiter.clone();
+        let start = Instant::now();
+        assert_eq!(limiter.reserve_at(start), Duration::new(0, 0));
+        assert_wait(clone.reserve_at(start), 1000);
+    }
+
+    #[test]
+    fn does_not_refill_when_the_clock_goes_back() {
+        let limiter = RateLimiter::new(&[RateLimit::per_second(1)]);
+        let start = Instant::now();
+        assert_eq!(limiter.reserve_at(after(start, 1000)), Duration::new(0, 0));
+        assert_wait(limiter.reserve_at(start), 1000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_an_empty_limit() {
+        RateLimit::per_second(0);
+    }
+}
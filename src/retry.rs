@@ -0,0 +1,220 @@
+use std::cmp;
+use std::io;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use rand;
+use reqwest::{Method, StatusCode};
+use reqwest::header::Headers;
+
+use Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// 失敗したリクエストのリトライ設定
+///
+/// 接続エラーとタイムアウト、429と5xxのレスポンスをリトライします。
+/// `Retry-After`ヘッダがあればその時間待ちますが、`max_delay`より長くは待ちません。
+pub struct RetryPolicy {
+    /// 最大試行回数（初回を含む）。1ならリトライしない
+    pub max_attempts: u32,
+    /// 最初のリトライまでの待ち時間。リトライ毎に倍になる
+    pub base_delay: Duration,
+    /// 待ち時間の上限
+    pub max_delay: Duration,
+    /// 待ち時間をランダムにばらつかせるか
+    pub jitter: bool,
+    /// POSTやPATCHのような冪等でないリクエストもリトライするか
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// リトライしない
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// `attempt`回目の試行が失敗したときに、次の試行までに待つ時間を返す。
+    /// リトライしないなら`None`
+    pub(crate) fn delay(&self, method: &Method, attempt: u32, failure: &Failure) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        if !(method.idempotent() || self.retry_non_idempotent) {
+            return None;
+        }
+        if !is_transient(&failure.error) {
+            return None;
+        }
+        if let Some(retry_after) = failure.retry_after {
+            return Some(cmp::min(retry_after, self.max_delay));
+        }
+        Some(self.backoff(attempt))
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let base = millis(self.base_delay);
+        let max = millis(self.max_delay);
+        let exp = cmp::min(attempt - 1, 31);
+        let delay = cmp::min(base.saturating_mul(1 << exp), max);
+        let delay = if self.jitter {
+            // 半分は固定、残り半分をランダムにする
+            delay / 2 + (rand::random::<f64>() * (delay / 2) as f64) as u64
+        } else {
+            delay
+        };
+        Duration::from_millis(delay)
+    }
+}
+
+/// 1回の試行の失敗
+pub(crate) struct Failure {
+    pub error: Error,
+    /// レスポンスの`Retry-After`ヘッダ
+    pub retry_after: Option<Duration>,
+}
+
+impl Failure {
+    pub fn new(error: Error, headers: Option<&Headers>) -> Self {
+        Self {
+            error,
+            retry_after: headers.and_then(retry_after),
+        }
+    }
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        Self::new(error, None)
+    }
+}
+
+fn is_transient(error: &Error) -> bool {
+    match *error {
+        // URLやリダイレクト、TLSのエラーは送り直しても同じなので、I/Oのエラー（接続やタイムアウト）だけ
+        Error::Http(ref e) => {
+            e.status().map_or(false, is_transient_status) ||
+                e.get_ref().map_or(false, |e| e.is::<io::Error>())
+        }
+        Error::Io(_) | Error::Timeout | Error::Transport(_) => true,
+        _ => error.status().map_or(false, is_transient_status),
+    }
+}
+
+/// 429と5xx
+fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TooManyRequests || status.is_server_error()
+}
+
+/// 秒数かHTTP-dateで書かれた`Retry-After`を読む
+fn retry_after(headers: &Headers) -> Option<Duration> {
+    let value = headers.get_raw("Retry-After")?.one()?;
+    let value = ::std::str::from_utf8(value).ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = date.with_timezone(&Utc)
+        .signed_duration_since(Utc::now())
+        .num_seconds();
+    Some(Duration::from_secs(cmp::max(secs, 0) as u64))
+}
+
+fn millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1000 + u64::from(duration.subsec_nanos() / 1_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_jitter() -> RetryPolicy {
+        RetryPolicy {
+            jitter: false,
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn response(status: StatusCode) -> Failure {
+        Failure::from(Error::UnexpectedResponse {
+            status,
+            body: String::new(),
+        })
+    }
+
+    #[test]
+    fn retry_after_is_capped_by_max_delay() {
+        let failure = Failure {
+            error: Error::Timeout,
+            retry_after: Some(Duration::from_secs(3600)),
+        };
+        let policy = no_jitter();
+        assert_eq!(
+            policy.delay(&Method::Get, 1, &failure),
+            Some(policy.max_delay)
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_without_retry_after() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            ..no_jitter()
+        };
+        let failure = Failure::from(Error::Timeout);
+        assert_eq!(
+            policy.delay(&Method::Get, 1, &failure),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            policy.delay(&Method::Get, 2, &failure),
+            Some(Duration::from_millis(1000))
+        );
+        assert_eq!(policy.delay(&Method::Get, 4, &failure), None);
+    }
+
+    #[test]
+    fn retries_too_many_requests_and_server_errors_only() {
+        let policy = no_jitter();
+        assert!(
+            policy
+                .delay(&Method::Get, 1, &response(StatusCode::TooManyRequests))
+                .is_some()
+        );
+        assert!(
+            policy
+                .delay(&Method::Get, 1, &response(StatusCode::InternalServerError))
+                .is_some()
+        );
+        assert!(
+            policy
+                .delay(&Method::Get, 1, &response(StatusCode::NotFound))
+                .is_none()
+        );
+        assert!(
+            policy
+                .delay(&Method::Get, 1, &Failure::from(Error::InvalidQuantity("x".into())))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn does_not_retry_non_idempotent_requests_by_default() {
+        let failure = Failure::from(Error::Timeout);
+        assert_eq!(no_jitter().delay(&Method::Post, 1, &failure), None);
+    }
+}
@@ -0,0 +1,165 @@
+//! 請求書の検索条件
+//!
+//! ```ignore
+//! let search = BillingSearch::new()
+//!     .query("サンプル")
+//!     .range(RangeKey::BillingDate, Some(NaiveDate::from_ymd(2017, 9, 1)), None)?;
+//! for billing in client.billings_search(search).iter() {
+//!     println!("{}", billing?.id);
+//! }
+//! ```
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+use {Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// 期間で絞り込む対象
+pub enum RangeKey {
+    /// 請求日
+    BillingDate,
+    /// 支払い期日
+    DueDate,
+    /// 売上日
+    SalesDate,
+    /// 作成日時
+    CreatedAt,
+    /// 更新日時
+    UpdatedAt,
+}
+
+impl RangeKey {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            RangeKey::BillingDate => "billing_date",
+            RangeKey::DueDate => "due_date",
+            RangeKey::SalesDate => "sales_date",
+            RangeKey::CreatedAt => "created_at",
+            RangeKey::UpdatedAt => "updated_at",
+        }
+    }
+}
+
+impl fmt::Display for RangeKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for RangeKey {
+    type Err = ParseRangeKeyError;
+
+    fn from_str(s: &str) -> ::std::result::Result<Self, Self::Err> {
+        match s {
+            "billing_date" => Ok(RangeKey::BillingDate),
+            "due_date" => Ok(RangeKey::DueDate),
+            "sales_date" => Ok(RangeKey::SalesDate),
+            "created_at" => Ok(RangeKey::CreatedAt),
+            "updated_at" => Ok(RangeKey::UpdatedAt),
+            _ => Err(ParseRangeKeyError(s.into())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// `RangeKey`として読めない文字列だった
+pub struct ParseRangeKeyError(String);
+
+impl fmt::Display for ParseRangeKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown range key: {}", self.0)
+    }
+}
+
+impl StdError for ParseRangeKeyError {
+    fn description(&self) -> &str {
+        "unknown range key"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+/// 請求書の検索条件
+pub struct BillingSearch {
+    query: String,
+    range_key: Option<RangeKey>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+impl BillingSearch {
+    /// 全ての請求書に一致する条件
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 検索文字列。前後の空白は取り除く
+    pub fn query<S: Into<String>>(mut self, query: S) -> Self {
+        self.query = query.into().trim().into();
+        self
+    }
+
+    /// `key`の日付が`from`から`to`まで（両端を含む）の請求書に絞る。`None`の側は絞らない
+    ///
+    /// `from`が`to`より後なら`Error::InvalidDateRange`を返します。
+    pub fn range(
+        mut self,
+        key: RangeKey,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> Result<Self> {
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(Error::InvalidDateRange { from, to });
+            }
+        }
+        self.range_key = Some(key);
+        self.from = from;
+        self.to = to;
+        Ok(self)
+    }
+
+    /// 検索文字列
+    pub fn query_str(&self) -> &str {
+        &self.query
+    }
+
+    /// 期間で絞り込む対象
+    pub fn range_key(&self) -> Option<RangeKey> {
+        self.range_key
+    }
+
+    /// 期間開始日
+    pub fn from(&self) -> Option<NaiveDate> {
+        self.from
+    }
+
+    /// 期間終了日
+    pub fn to(&self) -> Option<NaiveDate> {
+        self.to
+    }
+
+    /// 検索APIのクエリパラメータ
+    pub(crate) fn params(&self, page: u32, per_page: u32) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("page", page.to_string()),
+            ("per_page", per_page.to_string()),
+            ("q", self.query.clone()),
+        ];
+        if let Some(range_key) = self.range_key {
+            params.push(("range_key", range_key.as_str().into()));
+        }
+        if let Some(from) = self.from {
+            params.push(("from", from.to_string()));
+        }
+        if let Some(to) = self.to {
+            params.push(("to", to.to_string()));
+        }
+        params
+    }
+}
@@ -0,0 +1,769 @@
+//! 消費税と合計額の計算
+//!
+//! サーバが計算する小計・消費税・合計を請求書を作る前に手元で求めます。
+//! 消費税は品目の税区分ごとに合計してから丸めます。
+//! 金額が`i64`に収まらなければ`Error::AmountOverflow`を、税率の分からない税区分があれば`Error::UnknownTaxCategory`を返します。
+//!
+//! ```ignore
+//! let totals = TaxCalculator::new(10, Rounding::Down).new_billing(&new_billing)?;
+//! let billing = client.create_billing(new_billing)?;
+//! assert!(totals.matches(&billing));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use model::{Billing, BillingItem, NewBilling, NewBillingItem, TaxSummary};
+use money::Yen;
+use patch::Patch;
+use {Error, Result};
+
+/// 標準税率（%）
+pub const STANDARD_RATE: u32 = 10;
+/// 軽減税率（%）
+pub const REDUCED_RATE: u32 = 8;
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// 品目の税区分
+///
+/// 以前のAPIは課税対象かどうかを真偽値で返していたので、`true`は標準税率、`false`は非課税として読みます。
+/// リクエストでも標準税率と非課税は以前と同じ真偽値で送り、それ以外の区分だけAPIでの表記で送ります。
+pub enum TaxCategory {
+    /// 標準税率 10%
+    Standard,
+    /// 軽減税率 8%
+    Reduced,
+    /// 旧税率 8%
+    EightPercent,
+    /// 旧税率 5%
+    FivePercent,
+    /// 非課税
+    Exempt,
+    /// 不課税（課税対象外）
+    OutOfScope,
+    /// 知らない値
+    Unknown(String),
+}
+
+impl Default for TaxCategory {
+    /// 標準税率
+    fn default() -> Self {
+        TaxCategory::Standard
+    }
+}
+
+impl TaxCategory {
+    /// APIでの表記 e.g. "ten_percent"
+    pub fn as_str(&self) -> &str {
+        match *self {
+            TaxCategory::Standard => "ten_percent",
+            TaxCategory::Reduced => "eight_percent_as_reduced_tax_rate",
+            TaxCategory::EightPercent => "eight_percent",
+            TaxCategory::FivePercent => "five_percent",
+            TaxCategory::Exempt => "non_taxable",
+            TaxCategory::OutOfScope => "untaxable",
+            TaxCategory::Unknown(ref value) => value,
+        }
+    }
+
+    /// 税率（%）。課税されない区分と知らない区分は`None`
+    pub fn rate(&self) -> Option<u32> {
+        match *self {
+            TaxCategory::Standard => Some(STANDARD_RATE),
+            TaxCategory::Reduced | TaxCategory::EightPercent => Some(REDUCED_RATE),
+            TaxCategory::FivePercent => Some(5),
+            TaxCategory::Exempt | TaxCategory::OutOfScope | TaxCategory::Unknown(_) => None,
+        }
+    }
+
+    /// 消費税がかかるか
+    pub fn is_taxable(&self) -> bool {
+        self.rate().is_some()
+    }
+}
+
+impl fmt::Display for TaxCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            TaxCategory::Standard => "10%",
+            TaxCategory::Reduced => "8%（軽減税率）",
+            TaxCategory::EightPercent => "8%",
+            TaxCategory::FivePercent => "5%",
+            TaxCategory::Exempt => "非課税",
+            TaxCategory::OutOfScope => "不課税",
+            TaxCategory::Unknown(ref value) => value,
+        })
+    }
+}
+
+impl From<String> for TaxCategory {
+    /// APIでの表記のほか"true"、"false"も読む
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "ten_percent" | "true" => TaxCategory::Standard,
+            "eight_percent_as_reduced_tax_rate" => TaxCategory::Reduced,
+            "eight_percent" => TaxCategory::EightPercent,
+            "five_percent" => TaxCategory::FivePercent,
+            "non_taxable" | "false" => TaxCategory::Exempt,
+            "untaxable" => TaxCategory::OutOfScope,
+            _ => TaxCategory::Unknown(value),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for TaxCategory {
+    fn from(value: &'a str) -> Self {
+        TaxCategory::from(value.to_string())
+    }
+}
+
+impl Serialize for TaxCategory {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for TaxCategory {
+    fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(TaxCategoryVisitor)
+    }
+}
+
+struct TaxCategoryVisitor;
+
+impl<'de> Visitor<'de> for TaxCategoryVisitor {
+    type Value = TaxCategory;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a tax category as a string or a boolean")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> ::std::result::Result<TaxCategory, E> {
+        Ok(if v {
+            TaxCategory::Standard
+        } else {
+            TaxCategory::Exempt
+        })
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> ::std::result::Result<TaxCategory, E> {
+        Ok(TaxCategory::from(v))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> ::std::result::Result<TaxCategory, E> {
+        Ok(TaxCategory::from(v))
+    }
+}
+
+/// リクエストでの税区分の表記
+struct RequestExcise<'a>(&'a TaxCategory);
+
+impl<'a> Serialize for RequestExcise<'a> {
+    fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self.0 {
+            TaxCategory::Standard => serializer.serialize_bool(true),
+            TaxCategory::Exempt => serializer.serialize_bool(false),
+            ref category => category.serialize(serializer),
+        }
+    }
+}
+
+/// リクエストの品目の税区分を書く。標準税率と非課税は真偽値にする
+pub(crate) fn serialize_excise<S>(
+    category: &TaxCategory,
+    serializer: S,
+) -> ::std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    RequestExcise(category).serialize(serializer)
+}
+
+/// `serialize_excise`の`Option`版
+pub(crate) fn serialize_optional_excise<S>(
+    category: &Option<TaxCategory>,
+    serializer: S,
+) -> ::std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    category.as_ref().map(RequestExcise).serialize(serializer)
+}
+
+/// `serialize_excise`の`Patch`版
+pub(crate) fn serialize_excise_patch<S>(
+    category: &Patch<TaxCategory>,
+    serializer: S,
+) -> ::std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match *category {
+        Patch::Set(ref category) => serializer.serialize_some(&RequestExcise(category)),
+        Patch::Unchanged | Patch::Clear => serializer.serialize_none(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// 端数の処理
+pub enum Rounding {
+    /// 切り捨て
+    Down,
+    /// 四捨五入
+    HalfUp,
+    /// 切り上げ
+    Up,
+}
+
+impl Default for Rounding {
+    /// 切り捨て。サービスのデフォルトと同じ
+    fn default() -> Self {
+        Rounding::Down
+    }
+}
+
+impl fmt::Display for Rounding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Rounding::Down => "切り捨て",
+            Rounding::HalfUp => "四捨五入",
+            Rounding::Up => "切り上げ",
+        })
+    }
+}
+
+impl Rounding {
+    /// `numerator / denominator`を円単位に丸める。負の金額は絶対値を丸める
+    pub(crate) fn divide(&self, numerator: i64, denominator: i64) -> Result<Yen> {
+        debug_assert!(denominator > 0);
+        let sign = if numerator < 0 { -1 } else { 1 };
+        let numerator = numerator.checked_abs().ok_or(Error::AmountOverflow)?;
+        let quotient = numerator / denominator;
+        let remainder = numerator % denominator;
+        let rounded = match *self {
+            Rounding::Down => quotient,
+            Rounding::HalfUp if remainder * 2 >= denominator => quotient + 1,
+            Rounding::HalfUp => quotient,
+            Rounding::Up if remainder > 0 => quotient + 1,
+            Rounding::Up => quotient,
+        };
+        Ok(Yen(sign * rounded))
+    }
+}
+
+/// 税額を計算できる品目
+pub trait TaxableItem {
+    /// 数量を10の`scale`乗倍した整数と`scale`の組 e.g. "1.5"なら`(15, 1)`
+    fn quantity(&self) -> Result<(i64, u32)>;
+
+    /// 単価。なければ金額は0
+    fn unit_price(&self) -> Option<Yen>;
+
+    /// 税区分
+    fn tax_category(&self) -> TaxCategory;
+}
+
+impl TaxableItem for BillingItem {
+    fn quantity(&self) -> Result<(i64, u32)> {
+        Ok((i64::from(self.quantity.unwrap_or(1)), 0))
+    }
+
+    fn unit_price(&self) -> Option<Yen> {
+        self.unit_price
+    }
+
+    fn tax_category(&self) -> TaxCategory {
+        self.excise.clone()
+    }
+}
+
+impl TaxableItem for NewBillingItem {
+    fn quantity(&self) -> Result<(i64, u32)> {
+        match self.quantity {
+            Some(ref quantity) if !quantity.trim().is_empty() => parse_quantity(quantity),
+            _ => Ok((1, 0)),
+        }
+    }
+
+    fn unit_price(&self) -> Option<Yen> {
+        self.unit_price
+    }
+
+    fn tax_category(&self) -> TaxCategory {
+        self.excise.clone()
+    }
+}
+
+/// 小数点以下3桁までの数量を読む
+pub(crate) fn parse_quantity(quantity: &str) -> Result<(i64, u32)> {
+    let error = || Error::InvalidQuantity(quantity.into());
+    let quantity = quantity.trim();
+    let (integer, fraction) = match quantity.find('.') {
+        Some(i) => (&quantity[..i], &quantity[i + 1..]),
+        None => (quantity, ""),
+    };
+    if fraction.len() > 3 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(error());
+    }
+    let scaled: i64 = format!("{}{}", integer, fraction)
+        .parse()
+        .map_err(|_| error())?;
+    Ok((scaled, fraction.len() as u32))
+}
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// 品目1行の金額
+pub struct LineTotal {
+    /// 単価×数量を丸めた金額
+    pub price: Yen,
+    /// 税区分
+    pub tax_category: TaxCategory,
+}
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// 請求書全体の金額
+pub struct Totals {
+    /// 品目毎の金額。品目と同じ順
+    pub lines: Vec<LineTotal>,
+    /// 税区分ごとの小計と消費税。品目に現れる区分だけを`TaxCategory`の順に並べる
+    pub summaries: Vec<TaxSummary>,
+    /// 小計
+    pub subtotal: Yen,
+    /// 消費税
+    pub excise_price: Yen,
+    /// 合計
+    pub total_price: Yen,
+}
+
+impl Totals {
+    /// サーバが返した請求書の金額と一致するか。税区分ごとの内訳は返ってきた時だけ比べる
+    pub fn matches(&self, billing: &Billing) -> bool {
+        let lines_match = self.lines.len() == billing.items.len() &&
+            self.lines.iter().zip(&billing.items).all(|(line, item)| {
+                // 単価のない品目は金額も返ってこない
+                item.price.unwrap_or_default() == line.price
+            });
+        let summaries_match =
+            billing.tax_summaries.is_empty() || self.summaries == billing.tax_summaries;
+        lines_match && summaries_match && self.subtotal == billing.subtotal &&
+            self.excise_price == billing.excise_price &&
+            self.total_price == billing.total_price
+    }
+}
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// 消費税の計算方法
+///
+/// 品目毎の金額は単価×数量を丸め、消費税は税区分ごとに品目の合計に税率を掛けてから丸めます。
+/// 税率は`with_rate`で変えない限り`TaxCategory::rate`です。
+pub struct TaxCalculator {
+    rates: BTreeMap<TaxCategory, u32>,
+    rounding: Rounding,
+}
+
+impl Default for TaxCalculator {
+    /// 標準税率10%、軽減税率8%、切り捨て
+    fn default() -> Self {
+        Self::new(STANDARD_RATE, Rounding::default())
+    }
+}
+
+impl TaxCalculator {
+    /// 標準税率（%）と端数の処理を与える
+    ///
+    /// ```ignore
+    /// let calculator = TaxCalculator::new(10, Rounding::HalfUp);
+    /// ```
+    pub fn new(rate: u32, rounding: Rounding) -> Self {
+        Self {
+            rates: BTreeMap::new(),
+            rounding,
+        }.with_rate(TaxCategory::Standard, rate)
+    }
+
+    /// `category`の税率（%）を変える。知らない区分にも税率を与えられる
+    ///
+    /// ```ignore
+    /// let calculator = TaxCalculator::default().with_rate(TaxCategory::Reduced, 6);
+    /// ```
+    pub fn with_rate(mut self, category: TaxCategory, rate: u32) -> Self {
+        self.rates.insert(category, rate);
+        self
+    }
+
+    /// 標準税率（%）
+    pub fn rate(&self) -> u32 {
+        self.rate_for(&TaxCategory::Standard).unwrap_or(STANDARD_RATE)
+    }
+
+    /// `category`の税率（%）。課税されない区分は`None`
+    pub fn rate_for(&self, category: &TaxCategory) -> Option<u32> {
+        self.rates.get(category).cloned().or_else(|| category.rate())
+    }
+
+    /// 端数の処理
+    pub fn rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    /// 品目1行の金額
+    pub fn line<I: TaxableItem>(&self, item: &I) -> Result<LineTotal> {
+        let (quantity, scale) = item.quantity()?;
+        let unit_price = item.unit_price().unwrap_or_default();
+        let amount = unit_price
+            .checked_mul(quantity)
+            .ok_or(Error::AmountOverflow)?;
+        Ok(LineTotal {
+            price: self.rounding.divide(amount.amount(), 10i64.pow(scale))?,
+            tax_category: item.tax_category(),
+        })
+    }
+
+    /// 品目の一覧から請求書全体の金額を求める
+    pub fn items<I: TaxableItem>(&self, items: &[I]) -> Result<Totals> {
+        let lines = items
+            .iter()
+            .map(|item| self.line(item))
+            .collect::<Result<Vec<_>>>()?;
+        let mut categories: Vec<TaxCategory> = lines
+            .iter()
+            .map(|line| line.tax_category.clone())
+            .collect();
+        categories.sort();
+        categories.dedup();
+        let summaries = categories
+            .into_iter()
+            .map(|category| {
+                let subtotal = checked_sum(
+                    lines
+                        .iter()
+                        .filter(|line| line.tax_category == category)
+                        .map(|line| line.price),
+                )?;
+                Ok(TaxSummary {
+                    excise_price: self.tax(&category, subtotal)?,
+                    excise: category,
+                    subtotal,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let subtotal = checked_sum(summaries.iter().map(|summary| summary.subtotal))?;
+        let excise_price = checked_sum(summaries.iter().map(|summary| summary.excise_price))?;
+        Ok(Totals {
+            lines,
+            summaries,
+            subtotal,
+            excise_price,
+            total_price: checked_sum(vec![subtotal, excise_price])?,
+        })
+    }
+
+    /// 作成する請求書の金額
+    pub fn new_billing(&self, billing: &NewBilling) -> Result<Totals> {
+        self.items(&billing.items)
+    }
+
+    /// 取得した請求書の金額を計算し直す
+    pub fn billing(&self, billing: &Billing) -> Result<Totals> {
+        self.items(&billing.items)
+    }
+
+    /// `category`の税抜金額に対する消費税
+    ///
+    /// 課税されない区分は0円です。税率を与えていない`TaxCategory::Unknown`は`Error::UnknownTaxCategory`になります。
+    pub fn tax(&self, category: &TaxCategory, amount: Yen) -> Result<Yen> {
+        match self.rate_for(category) {
+            Some(rate) => {
+                let amount = amount
+                    .amount()
+                    .checked_mul(i64::from(rate))
+                    .ok_or(Error::AmountOverflow)?;
+                self.rounding.divide(amount, 100)
+            }
+            None => match *category {
+                TaxCategory::Unknown(_) => Err(Error::UnknownTaxCategory(category.clone())),
+                _ => Ok(Yen(0)),
+            },
+        }
+    }
+}
+
+/// 桁あふれを確かめながら金額を足す
+fn checked_sum<I: IntoIterator<Item = Yen>>(amounts: I) -> Result<Yen> {
+    amounts.into_iter().fold(Ok(Yen(0)), |sum, amount| {
+        sum?.checked_add(amount).ok_or(Error::AmountOverflow)
+    })
+}
+
+/// 品目に現れる全ての税区分について、請求書に内訳があるか確かめる
+///
+/// 適格請求書には税率ごとの内訳が必要です。足りなければ`Error::MissingTaxSummaries`を返します。
+pub fn check_summaries(billing: &Billing) -> Result<()> {
+    let missing = missing_summaries(billing);
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::MissingTaxSummaries(missing))
+    }
+}
+
+/// 品目に現れる税区分のうち、請求書に内訳のないもの
+pub(crate) fn missing_summaries(billing: &Billing) -> Vec<TaxCategory> {
+    let mut missing: Vec<TaxCategory> = billing
+        .items
+        .iter()
+        .map(|item| item.excise.clone())
+        .filter(|category| {
+            !billing
+                .tax_summaries
+                .iter()
+                .any(|summary| &summary.excise == category)
+        })
+        .collect();
+    missing.sort();
+    missing.dedup();
+    missing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn item(unit_price: i64, quantity: &str, excise: TaxCategory) -> NewBillingItem {
+        NewBillingItem {
+            unit_price: Some(Yen(unit_price)),
+            quantity: Some(quantity.into()),
+            excise,
+            ..Default::default()
+        }
+    }
+
+    fn price(rounding: Rounding, item: &NewBillingItem) -> Yen {
+        TaxCalculator::new(10, rounding).line(item).unwrap().price
+    }
+
+    #[test]
+    fn rounds_each_way() {
+        let half = item(333, "1.5", TaxCategory::Standard);
+        assert_eq!(price(Rounding::Down, &half), Yen(499));
+        assert_eq!(price(Rounding::HalfUp, &half), Yen(500));
+        assert_eq!(price(Rounding::Up, &half), Yen(500));
+
+        let below_half = item(333, "1.1", TaxCategory::Standard);
+        assert_eq!(price(Rounding::Down, &below_half), Yen(366));
+        assert_eq!(price(Rounding::HalfUp, &below_half), Yen(366));
+        assert_eq!(price(Rounding::Up, &below_half), Yen(367));
+    }
+
+    #[test]
+    fn rounds_negative_amounts_by_absolute_value() {
+        let discount = item(-333, "1.5", TaxCategory::Standard);
+        assert_eq!(price(Rounding::Down, &discount), Yen(-499));
+        assert_eq!(price(Rounding::HalfUp, &discount), Yen(-500));
+        assert_eq!(price(Rounding::Up, &discount), Yen(-500));
+    }
+
+    #[test]
+    fn reads_fractional_quantities() {
+        assert_eq!(parse_quantity("1.5").unwrap(), (15, 1));
+        assert_eq!(parse_quantity(" 0.125 ").unwrap(), (125, 3));
+        assert_eq!(parse_quantity("3").unwrap(), (3, 0));
+        assert_eq!(
+            price(Rounding::Down, &item(1000, "0.333", TaxCategory::Standard)),
+            Yen(333)
+        );
+        match parse_quantity("1.2345") {
+            Err(Error::InvalidQuantity(ref quantity)) => assert_eq!(quantity, "1.2345"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(parse_quantity("1.a").is_err());
+    }
+
+    #[test]
+    fn totals_lines_and_summaries_by_category() {
+        let items = vec![
+            item(1000, "2", TaxCategory::Standard),
+            item(1000, "1", TaxCategory::Reduced),
+            item(500, "1", TaxCategory::Exempt),
+            item(55, "1", TaxCategory::Standard),
+        ];
+        let totals = TaxCalculator::default().items(&items).unwrap();
+        let prices: Vec<Yen> = totals.lines.iter().map(|line| line.price).collect();
+        assert_eq!(prices, vec![Yen(2000), Yen(1000), Yen(500), Yen(55)]);
+        assert_eq!(
+            totals.summaries,
+            vec![
+                TaxSummary {
+                    excise: TaxCategory::Standard,
+                    subtotal: Yen(2055),
+                    excise_price: Yen(205),
+                },
+                TaxSummary {
+                    excise: TaxCategory::Reduced,
+                    subtotal: Yen(1000),
+                    excise_price: Yen(80),
+                },
+                TaxSummary {
+                    excise: TaxCategory::Exempt,
+                    subtotal: Yen(500),
+                    excise_price: Yen(0),
+                },
+            ]
+        );
+        assert_eq!(totals.subtotal, Yen(3555));
+        assert_eq!(totals.excise_price, Yen(285));
+        assert_eq!(totals.total_price, Yen(3840));
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        let calculator = TaxCalculator::default();
+        let huge = item(i64::max_value(), "2", TaxCategory::Standard);
+        match calculator.line(&huge) {
+            Err(Error::AmountOverflow) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let items = vec![
+            item(i64::max_value(), "1", TaxCategory::Exempt),
+            item(1, "1", TaxCategory::Exempt),
+        ];
+        match calculator.items(&items) {
+            Err(Error::AmountOverflow) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match calculator.tax(&TaxCategory::Standard, Yen(i64::max_value())) {
+            Err(Error::AmountOverflow) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reads_tax_categories() {
+        let read = |json: &str| serde_json::from_str::<TaxCategory>(json).unwrap();
+        assert_eq!(read(r#""ten_percent""#), TaxCategory::Standard);
+        assert_eq!(
+            read(r#""eight_percent_as_reduced_tax_rate""#),
+            TaxCategory::Reduced
+        );
+        assert_eq!(read(r#""eight_percent""#), TaxCategory::EightPercent);
+        assert_eq!(read(r#""five_percent""#), TaxCategory::FivePercent);
+        assert_eq!(read("true"), TaxCategory::Standard);
+        assert_eq!(read("false"), TaxCategory::Exempt);
+        assert_eq!(
+            read(r#""three_percent""#),
+            TaxCategory::Unknown("three_percent".into())
+        );
+        assert_eq!(
+            serde_json::to_string(&TaxCategory::Unknown("three_percent".into())).unwrap(),
+            r#""three_percent""#
+        );
+    }
+
+    #[test]
+    fn rates_are_configurable_per_category() {
+        let calculator = TaxCalculator::default();
+        assert_eq!(calculator.rate(), 10);
+        assert_eq!(calculator.rate_for(&TaxCategory::Reduced), Some(8));
+        assert_eq!(calculator.rate_for(&TaxCategory::FivePercent), Some(5));
+        assert_eq!(calculator.rate_for(&TaxCategory::Exempt), None);
+        assert_eq!(
+            calculator.rate_for(&TaxCategory::Unknown("three_percent".into())),
+            None
+        );
+
+        let calculator = TaxCalculator::new(8, Rounding::Down)
+            .with_rate(TaxCategory::Unknown("three_percent".into()), 3);
+        assert_eq!(calculator.rate(), 8);
+        assert_eq!(
+            calculator.tax(&TaxCategory::Standard, Yen(1000)).unwrap(),
+            Yen(80)
+        );
+        assert_eq!(
+            calculator
+                .tax(&TaxCategory::Unknown("three_percent".into()), Yen(1000))
+                .unwrap(),
+            Yen(30)
+        );
+        assert_eq!(
+            calculator.tax(&TaxCategory::FivePercent, Yen(1000)).unwrap(),
+            Yen(50)
+        );
+    }
+
+    #[test]
+    fn unknown_categories_need_a_rate() {
+        let three_percent = TaxCategory::Unknown("three_percent".into());
+        match TaxCalculator::default().tax(&three_percent, Yen(1000)) {
+            Err(Error::UnknownTaxCategory(ref category)) if *category == three_percent => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match TaxCalculator::default().items(&[item(1000, "1", three_percent.clone())]) {
+            Err(Error::UnknownTaxCategory(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // 課税されない区分は0円
+        assert_eq!(
+            TaxCalculator::default()
+                .tax(&TaxCategory::OutOfScope, Yen(1000))
+                .unwrap(),
+            Yen(0)
+        );
+    }
+
+    #[test]
+    fn requests_send_known_categories_as_booleans() {
+        use model::{NewItem, UpdateBillingItem, UpdateItem};
+        use serde_json::Value;
+
+        let write = |excise: TaxCategory| {
+            serde_json::to_value(&item(1000, "1", excise)).unwrap()["excise"].clone()
+        };
+        assert_eq!(write(TaxCategory::Standard), Value::from(true));
+        assert_eq!(write(TaxCategory::Exempt), Value::from(false));
+        assert_eq!(
+            write(TaxCategory::Reduced),
+            Value::from("eight_percent_as_reduced_tax_rate")
+        );
+        assert_eq!(write(TaxCategory::OutOfScope), Value::from("untaxable"));
+
+        let update = UpdateBillingItem {
+            excise: Patch::Set(TaxCategory::Exempt),
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(&update).unwrap()["excise"], Value::from(false));
+        let update = UpdateItem {
+            excise: Patch::Set(TaxCategory::Reduced),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&update).unwrap()["excise"],
+            Value::from("eight_percent_as_reduced_tax_rate")
+        );
+        assert!(serde_json::to_value(&UpdateItem::default()).unwrap()["excise"].is_null());
+        let new = NewItem {
+            excise: Some(TaxCategory::Standard),
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(&new).unwrap()["excise"], Value::from(true));
+
+        // 読む時はどちらの表記も受け付ける
+        let written = serde_json::to_value(&item(1000, "1", TaxCategory::Standard)).unwrap();
+        let read: NewBillingItem = serde_json::from_value(written).unwrap();
+        assert_eq!(read.excise, TaxCategory::Standard);
+    }
+}
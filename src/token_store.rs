@@ -0,0 +1,136 @@
+//! トークンの保存先
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json;
+
+use auth::Token;
+use {Error, Result};
+
+/// トークンの保存先
+///
+/// クライアントはここからトークンを読み、更新したトークンをここに書きます。
+pub trait TokenStore: Send + Sync {
+    /// 保存されているトークン。なければ`None`
+    fn load(&self) -> Result<Option<Token>>;
+
+    /// トークンを保存する
+    fn save(&self, token: &Token) -> Result<()>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// トークンをJSONでファイルに保存する
+///
+/// Unixではファイルのパーミッションを0600にします。
+pub struct FileTokenStore {
+    path: PathBuf,
+}
+
+impl FileTokenStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl TokenStore for FileTokenStore {
+    fn load(&self) -> Result<Option<Token>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut body = String::new();
+        file.read_to_string(&mut body)?;
+        serde_json::from_str(&body)
+            .map(Some)
+            .map_err(|error| Error::Decode { error, body })
+    }
+
+    fn save(&self, token: &Token) -> Result<()> {
+        let body = serde_json::to_vec_pretty(token).map_err(Error::Encode)?;
+        // 書き込み途中で壊れないように、一時ファイルに書いてから置き換える
+        let tmp = self.path.with_extension("tmp");
+        // 残っていた一時ファイルのパーミッションを引き継がないように消しておく
+        let _ = fs::remove_file(&tmp);
+        {
+            let mut file = create_private(&tmp)?;
+            file.write_all(&body)?;
+            file.sync_all()?;
+        }
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn create_private(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+}
+
+#[cfg(not(unix))]
+fn create_private(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// トークンを環境変数から読む
+///
+/// 保存はこのプロセス（と子プロセス）の環境変数を書き換えるだけなので、再起動すると失われます。
+pub struct EnvTokenStore {
+    access_token: String,
+    refresh_token: String,
+}
+
+impl Default for EnvTokenStore {
+    fn default() -> Self {
+        Self::new("MF_INVOICE_ACCESS_TOKEN", "MF_INVOICE_REFRESH_TOKEN")
+    }
+}
+
+impl EnvTokenStore {
+    /// アクセストークンとリフレッシュトークンを読む環境変数の名前を与える
+    pub fn new<S, T>(access_token: S, refresh_token: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            access_token: access_token.into(),
+            refresh_token: refresh_token.into(),
+        }
+    }
+}
+
+impl TokenStore for EnvTokenStore {
+    fn load(&self) -> Result<Option<Token>> {
+        let access_token = match env::var(&self.access_token) {
+            Ok(access_token) => access_token,
+            Err(_) => return Ok(None),
+        };
+        let mut token = Token::bearer(access_token);
+        token.refresh_token = env::var(&self.refresh_token).ok();
+        Ok(Some(token))
+    }
+
+    fn save(&self, token: &Token) -> Result<()> {
+        env::set_var(&self.access_token, &token.access_token);
+        if let Some(ref refresh_token) = token.refresh_token {
+            env::set_var(&self.refresh_token, refresh_token);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+//! HTTPの送受信
+//!
+//! `Client`は`Transport`を通してリクエストを送ります。
+//! テスト用の偽物や別のHTTPクライアントを使いたければ`ClientBuilder::transport`で差し替えて下さい。
+
+use std::io::Read;
+
+use reqwest;
+use reqwest::{Method, StatusCode, Url};
+use reqwest::header::Headers;
+
+use Result;
+
+#[derive(Debug, Clone)]
+/// 送るリクエスト
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: Headers,
+    pub body: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+/// 受け取ったレスポンス
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+/// リクエストを送ってレスポンスを受け取る
+///
+/// HTTPとして成功しなかったステータスもそのまま`HttpResponse`として返して下さい。
+/// 通信自体に失敗したら`Error::Http`か`Error::Transport`を返して下さい。
+pub trait Transport: Send + Sync {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse>;
+}
+
+#[derive(Debug, Clone)]
+/// reqwestによる`Transport`。デフォルトで使われる
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+        let mut reqbuilder = self.client.request(request.method, request.url);
+        reqbuilder.headers(request.headers);
+        if let Some(body) = request.body {
+            reqbuilder.body(body);
+        }
+        let mut res = reqbuilder.send()?;
+        let mut body = Vec::new();
+        res.read_to_end(&mut body)?;
+        Ok(HttpResponse {
+            status: res.status(),
+            headers: res.headers().clone(),
+            body,
+        })
+    }
+}
@@ -0,0 +1,688 @@
+//! 送信前のリクエストの検証
+//!
+//! APIに送る前に必須項目や文字数、郵便番号などの形式を確かめます。
+//! `Client`は作成・更新の前に自動で検証し、問題があれば`Error::Invalid`を返します。
+//!
+//! ```ignore
+//! if let Err(Error::Invalid(errors)) = new_partner.validate() {
+//!     for error in errors {
+//!         println!("{}", error);
+//!     }
+//! }
+//! ```
+
+use std::fmt;
+
+use chrono::NaiveDate;
+
+use model::*;
+use pagination::MAX_PER_PAGE;
+use patch::Patch;
+use tax;
+use {Error, Result};
+
+/// 1行の文字列の最大文字数
+pub const MAX_LENGTH: usize = 255;
+/// メモや備考など複数行の文字列の最大文字数
+pub const MAX_TEXT_LENGTH: usize = 2000;
+
+/// 送信前に検証できるリクエスト
+pub trait Validate {
+    /// 問題があれば全てのフィールドのエラーをまとめた`Error::Invalid`を返す
+    fn validate(&self) -> Result<()>;
+}
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// フィールド1つのエラー
+pub struct FieldError {
+    /// フィールド名 e.g. "items[0].quantity"
+    pub field: String,
+    /// エラーの種類
+    pub kind: FieldErrorKind,
+}
+
+#[derive(Debug, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
+/// フィールドのエラーの種類
+pub enum FieldErrorKind {
+    /// 必須なのに空
+    Required,
+    /// 最大文字数を超えている
+    TooLong(usize),
+    /// 郵便番号や電話番号などの形式が正しくない
+    InvalidFormat,
+    /// 最小値より小さい
+    TooSmall(u32),
+    /// 最大値より大きい
+    TooLarge(u32),
+    /// 指定したフィールドの日付より前
+    Before(String),
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            FieldErrorKind::Required => write!(f, "{} is required", self.field),
+            FieldErrorKind::TooLong(max) => {
+                write!(f, "{} is longer than {} characters", self.field, max)
+            }
+            FieldErrorKind::InvalidFormat => write!(f, "{} has an invalid format", self.field),
+            FieldErrorKind::TooSmall(min) => write!(f, "{} must be at least {}", self.field, min),
+            FieldErrorKind::TooLarge(max) => write!(f, "{} must be at most {}", self.field, max),
+            FieldErrorKind::Before(ref other) => {
+                write!(f, "{} must not be before {}", self.field, other)
+            }
+        }
+    }
+}
+
+/// フィールドのエラーを集める
+#[derive(Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn push<S: Into<String>>(&mut self, field: S, kind: FieldErrorKind) {
+        self.0.push(FieldError {
+            field: field.into(),
+            kind,
+        });
+    }
+
+    /// 必須の文字列
+    fn required(&mut self, field: &str, value: &str, max: usize) {
+        if value.trim().is_empty() {
+            self.push(field, FieldErrorKind::Required);
+        } else {
+            self.length(field, Some(value), max);
+        }
+    }
+
+    /// 変更しなくても良いが、空にはできない文字列
+    fn not_blank(&mut self, field: &str, value: &Patch<String>, max: usize) {
+        match *value {
+            Patch::Unchanged => {}
+            Patch::Clear => self.push(field, FieldErrorKind::Required),
+            Patch::Set(ref value) => self.required(field, value, max),
+        }
+    }
+
+    fn length(&mut self, field: &str, value: Option<&str>, max: usize) {
+        if value.map_or(false, |value| value.chars().count() > max) {
+            self.push(field, FieldErrorKind::TooLong(max));
+        }
+    }
+
+    /// 空でなければ`valid`な形式か確かめる
+    fn format(&mut self, field: &str, value: Option<&str>, valid: fn(&str) -> bool) {
+        match value {
+            Some(value) if !value.trim().is_empty() && !valid(value.trim()) => {
+                self.push(field, FieldErrorKind::InvalidFormat)
+            }
+            _ => {}
+        }
+    }
+
+    fn date_order(
+        &mut self,
+        (from_field, from): (&str, Option<NaiveDate>),
+        (to_field, to): (&str, Option<NaiveDate>),
+    ) {
+        if let (Some(from), Some(to)) = (from, to) {
+            if to < from {
+                self.push(to_field, FieldErrorKind::Before(from_field.into()));
+            }
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Invalid(self.0))
+        }
+    }
+}
+
+fn opt(value: &Option<String>) -> Option<&str> {
+    value.as_ref().map(String::as_str)
+}
+
+fn patch(value: &Patch<String>) -> Option<&str> {
+    value.value().map(String::as_str)
+}
+
+/// e.g. "123-4567", "1234567"
+fn is_zip(zip: &str) -> bool {
+    let digits = zip.chars().filter(|c| c.is_ascii_digit()).count();
+    let bytes = zip.as_bytes();
+    match zip.len() {
+        7 => digits == 7,
+        8 => digits == 7 && bytes[3] == b'-',
+        _ => false,
+    }
+}
+
+/// e.g. "03-1234-5678", "+81-3-1234-5678"
+fn is_phone(tel: &str) -> bool {
+    let digits = tel.chars().filter(|c| c.is_ascii_digit()).count();
+    let allowed = tel.chars()
+        .all(|c| c.is_ascii_digit() || "-+() ".contains(c));
+    allowed && 6 <= digits && digits <= 15
+}
+
+/// e.g. "info@example.com"
+fn is_email(email: &str) -> bool {
+    let mut parts = email.splitn(2, '@');
+    let local = parts.next().unwrap_or("");
+    let domain = parts.next().unwrap_or("");
+    !local.is_empty() && !domain.contains('@') && !domain.starts_with('.') &&
+        !domain.ends_with('.') && domain.contains('.') &&
+        !email.chars().any(|c| c.is_whitespace() || c == ',')
+}
+
+/// カンマ区切りのメールアドレス
+fn is_emails(emails: &str) -> bool {
+    emails.split(',').all(|email| is_email(email.trim()))
+}
+
+/// 一覧APIのページ指定
+pub(crate) fn page(page: u32, per_page: u32) -> Result<()> {
+    let mut errors = Errors::default();
+    if page < 1 {
+        errors.push("page", FieldErrorKind::TooSmall(1));
+    }
+    if per_page < 1 {
+        errors.push("per_page", FieldErrorKind::TooSmall(1));
+    } else if per_page > MAX_PER_PAGE {
+        errors.push("per_page", FieldErrorKind::TooLarge(MAX_PER_PAGE));
+    }
+    errors.finish()
+}
+
+impl Validate for UpdateOffice {
+    fn validate(&self) -> Result<()> {
+        let mut errors = Errors::default();
+        errors.not_blank("name", &self.name, MAX_LENGTH);
+        errors.format("zip", patch(&self.zip), is_zip);
+        errors.length("prefecture", patch(&self.prefecture), MAX_LENGTH);
+        errors.length("address1", patch(&self.address1), MAX_LENGTH);
+        errors.length("address2", patch(&self.address2), MAX_LENGTH);
+        errors.format("tel", patch(&self.tel), is_phone);
+        errors.format("fax", patch(&self.fax), is_phone);
+        errors.finish()
+    }
+}
+
+impl Validate for NewPartner {
+    fn validate(&self) -> Result<()> {
+        let mut errors = Errors::default();
+        errors.length("code", opt(&self.code), MAX_LENGTH);
+        errors.required("name", &self.name, MAX_LENGTH);
+        errors.length("name_kana", opt(&self.name_kana), MAX_LENGTH);
+        errors.length("name_suffix", opt(&self.name_suffix), MAX_LENGTH);
+        errors.length("memo", opt(&self.memo), MAX_TEXT_LENGTH);
+        errors.format("zip", opt(&self.zip), is_zip);
+        errors.format("tel", opt(&self.tel), is_phone);
+        errors.length("prefecture", opt(&self.prefecture), MAX_LENGTH);
+        errors.length("address1", opt(&self.address1), MAX_LENGTH);
+        errors.length("address2", opt(&self.address2), MAX_LENGTH);
+        errors.length("person_name", opt(&self.person_name), MAX_LENGTH);
+        errors.length("person_title", opt(&self.person_title), MAX_LENGTH);
+        errors.length("department_name", opt(&self.department_name), MAX_LENGTH);
+        errors.format("email", opt(&self.email), is_email);
+        errors.format("cc_emails", opt(&self.cc_emails), is_emails);
+        errors.finish()
+    }
+}
+
+impl Validate for UpdatePartner {
+    fn validate(&self) -> Result<()> {
+        let mut errors = Errors::default();
+        errors.length("code", patch(&self.code), MAX_LENGTH);
+        errors.not_blank("name", &self.name, MAX_LENGTH);
+        errors.length("name_kana", patch(&self.name_kana), MAX_LENGTH);
+        errors.length("name_suffix", patch(&self.name_suffix), MAX_LENGTH);
+        errors.length("memo", patch(&self.memo), MAX_TEXT_LENGTH);
+        for (i, department) in self.departments.iter().enumerate() {
+            let field = |name: &str| format!("departments[{}].{}", i, name);
+            errors.format(&field("zip"), patch(&department.zip), is_zip);
+            errors.format(&field("tel"), patch(&department.tel), is_phone);
+            errors.length(&field("prefecture"), patch(&department.prefecture), MAX_LENGTH);
+            errors.length(&field("address1"), patch(&department.address1), MAX_LENGTH);
+            errors.length(&field("address2"), patch(&department.address2), MAX_LENGTH);
+            errors.length(&field("person_name"), patch(&department.person_name), MAX_LENGTH);
+            errors.length(&field("person_title"), patch(&department.person_title), MAX_LENGTH);
+            errors.length(&field("name"), patch(&department.name), MAX_LENGTH);
+            errors.format(&field("email"), patch(&department.email), is_email);
+            errors.format(&field("cc_emails"), patch(&department.cc_emails), is_emails);
+        }
+        errors.finish()
+    }
+}
+
+impl Validate for NewBilling {
+    fn validate(&self) -> Result<()> {
+        let mut errors = Errors::default();
+        errors.required("department_id", self.department_id.as_str(), MAX_LENGTH);
+        errors.length("title", opt(&self.title), MAX_LENGTH);
+        errors.length("billing_number", opt(&self.billing_number), MAX_LENGTH);
+        errors.length("payment_condition", opt(&self.payment_condition), MAX_TEXT_LENGTH);
+        errors.length("note", opt(&self.note), MAX_TEXT_LENGTH);
+        errors.date_order(
+            ("billing_date", self.billing_date),
+            ("due_date", self.due_date),
+        );
+        errors.length("memo", opt(&self.memo), MAX_TEXT_LENGTH);
+        errors.length("document_name", opt(&self.document_name), MAX_LENGTH);
+        for (i, item) in self.items.iter().enumerate() {
+            let field = |name: &str| format!("items[{}].{}", i, name);
+            errors.length(&field("name"), opt(&item.name), MAX_LENGTH);
+            errors.length(&field("code"), opt(&item.code), MAX_LENGTH);
+            errors.length(&field("detail"), opt(&item.detail), MAX_TEXT_LENGTH);
+            errors.format(&field("quantity"), opt(&item.quantity), is_quantity);
+            errors.length(&field("unit"), opt(&item.unit), MAX_LENGTH);
+        }
+        errors.finish()
+    }
+}
+
+impl Validate for UpdateBilling {
+    fn validate(&self) -> Result<()> {
+        let mut errors = Errors::default();
+        match self.department_id {
+            Patch::Unchanged => {}
+            Patch::Clear => errors.push("department_id", FieldErrorKind::Required),
+            Patch::Set(ref id) => errors.required("department_id", id.as_str(), MAX_LENGTH),
+        }
+        errors.length("title", patch(&self.title), MAX_LENGTH);
+        errors.not_blank("billing_number", &self.billing_number, MAX_LENGTH);
+        errors.length("payment_condition", patch(&self.payment_condition), MAX_TEXT_LENGTH);
+        errors.length("note", patch(&self.note), MAX_TEXT_LENGTH);
+        errors.date_order(
+            ("billing_date", self.billing_date.value().cloned()),
+            ("due_date", self.due_date.value().cloned()),
+        );
+        errors.length("memo", patch(&self.memo), MAX_TEXT_LENGTH);
+        errors.length("document_name", patch(&self.document_name), MAX_LENGTH);
+        for (i, item) in self.items.iter().enumerate() {
+            if item._destroy {
+                continue;
+            }
+            let field = |name: &str| format!("items[{}].{}", i, name);
+            errors.length(&field("name"), patch(&item.name), MAX_LENGTH);
+            errors.length(&field("code"), patch(&item.code), MAX_LENGTH);
+            errors.length(&field("detail"), patch(&item.detail), MAX_TEXT_LENGTH);
+            errors.format(&field("quantity"), patch(&item.quantity), is_quantity);
+            errors.length(&field("unit"), patch(&item.unit), MAX_LENGTH);
+        }
+        errors.finish()
+    }
+}
+
+impl Validate for NewItem {
+    fn validate(&self) -> Result<()> {
+        let mut errors = Errors::default();
+        errors.required("name", &self.name, MAX_LENGTH);
+        errors.length("code", opt(&self.code), MAX_LENGTH);
+        errors.length("detail", opt(&self.detail), MAX_TEXT_LENGTH);
+        errors.length("unit", opt(&self.unit), MAX_LENGTH);
+        errors.finish()
+    }
+}
+
+impl Validate for UpdateItem {
+    fn validate(&self) -> Result<()> {
+        let mut errors = Errors::default();
+        errors.not_blank("name", &self.name, MAX_LENGTH);
+        errors.length("code", patch(&self.code), MAX_LENGTH);
+        errors.length("detail", patch(&self.detail), MAX_TEXT_LENGTH);
+        errors.length("unit", patch(&self.unit), MAX_LENGTH);
+        errors.finish()
+    }
+}
+
+/// e.g. "1", "1.5"
+fn is_quantity(quantity: &str) -> bool {
+    tax::parse_quantity(quantity).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use id::{BillingItemId, DepartmentId};
+
+    /// 検証で見つかったエラー。問題がなければ空
+    fn errors<V: Validate>(value: &V) -> Vec<FieldError> {
+        match value.validate() {
+            Ok(()) => Vec::new(),
+            Err(Error::Invalid(errors)) => errors,
+            Err(other) => panic!("unexpected error: {}", other),
+        }
+    }
+
+    fn error(field: &str, kind: FieldErrorKind) -> FieldError {
+        FieldError {
+            field: field.into(),
+            kind,
+        }
+    }
+
+    fn long(length: usize) -> String {
+        "あ".repeat(length)
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(year, month, day)
+    }
+
+    #[test]
+    fn checks_zip_codes() {
+        assert!(is_zip("123-4567"));
+        assert!(is_zip("1234567"));
+        assert!(!is_zip("12-34567"));
+        assert!(!is_zip("123-456"));
+        assert!(!is_zip("12345678"));
+        assert!(!is_zip("１２３-４５６７"));
+    }
+
+    #[test]
+    fn checks_phone_numbers() {
+        assert!(is_phone("03-1234-5678"));
+        assert!(is_phone("+81-3-1234-5678"));
+        assert!(is_phone("(03) 1234 5678"));
+        assert!(!is_phone("12345"));
+        assert!(!is_phone("0312345678901234"));
+        assert!(!is_phone("03-1234-567x"));
+    }
+
+    #[test]
+    fn checks_email_addresses() {
+        assert!(is_email("info@example.com"));
+        assert!(is_email("first.last+tag@sub.example.co.jp"));
+        assert!(!is_email("example.com"));
+        assert!(!is_email("@example.com"));
+        assert!(!is_email("info@example"));
+        assert!(!is_email("info@.example.com"));
+        assert!(!is_email("info@example.com."));
+        assert!(!is_email("info@a@example.com"));
+        assert!(!is_email("in fo@example.com"));
+
+        assert!(is_emails("a@example.com"));
+        assert!(is_emails("a@example.com, b@example.com"));
+        assert!(!is_emails("a@example.com,"));
+        assert!(!is_emails("a@example.com, b"));
+    }
+
+    #[test]
+    fn checks_quantities() {
+        assert!(is_quantity("1"));
+        assert!(is_quantity("1.5"));
+        assert!(is_quantity("0.125"));
+        assert!(!is_quantity("1.2345"));
+        assert!(!is_quantity("一"));
+    }
+
+    #[test]
+    fn checks_pages() {
+        assert!(page(1, 1).is_ok());
+        assert!(page(10, MAX_PER_PAGE).is_ok());
+        match page(0, MAX_PER_PAGE + 1) {
+            Err(Error::Invalid(errors)) => assert_eq!(
+                errors,
+                vec![
+                    error("page", FieldErrorKind::TooSmall(1)),
+                    error("per_page", FieldErrorKind::TooLarge(MAX_PER_PAGE)),
+                ]
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match page(1, 0) {
+            Err(Error::Invalid(errors)) => {
+                assert_eq!(errors, vec![error("per_page", FieldErrorKind::TooSmall(1))])
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn not_blank_depends_on_the_patch() {
+        let office = |name| UpdateOffice {
+            name,
+            ..Default::default()
+        };
+        assert!(errors(&office(Patch::Unchanged)).is_empty());
+        assert!(errors(&office(Patch::Set("事業所".into()))).is_empty());
+        assert_eq!(
+            errors(&office(Patch::Clear)),
+            vec![error("name", FieldErrorKind::Required)]
+        );
+        assert_eq!(
+            errors(&office(Patch::Set("  ".into()))),
+            vec![error("name", FieldErrorKind::Required)]
+        );
+        assert_eq!(
+            errors(&office(Patch::Set(long(MAX_LENGTH + 1)))),
+            vec![error("name", FieldErrorKind::TooLong(MAX_LENGTH))]
+        );
+        assert!(errors(&office(Patch::Set(long(MAX_LENGTH)))).is_empty());
+    }
+
+    #[test]
+    fn validates_update_office() {
+        let office = UpdateOffice {
+            zip: Patch::Set("123-4567".into()),
+            tel: Patch::Set("03-1234-5678".into()),
+            fax: Patch::Clear,
+            ..Default::default()
+        };
+        assert!(errors(&office).is_empty());
+
+        let office = UpdateOffice {
+            zip: Patch::Set("1234".into()),
+            address1: Patch::Set(long(MAX_LENGTH + 1)),
+            tel: Patch::Set("電話".into()),
+            fax: Patch::Set("fax".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            errors(&office),
+            vec![
+                error("zip", FieldErrorKind::InvalidFormat),
+                error("address1", FieldErrorKind::TooLong(MAX_LENGTH)),
+                error("tel", FieldErrorKind::InvalidFormat),
+                error("fax", FieldErrorKind::InvalidFormat),
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_new_partner() {
+        let partner = NewPartner {
+            name: "取引先".into(),
+            zip: Some(String::new()),
+            email: Some("info@example.com".into()),
+            cc_emails: Some("a@example.com,b@example.com".into()),
+            ..Default::default()
+        };
+        assert!(errors(&partner).is_empty());
+
+        let partner = NewPartner {
+            memo: Some(long(MAX_TEXT_LENGTH + 1)),
+            tel: Some("0".into()),
+            email: Some("info".into()),
+            cc_emails: Some("a@example.com,b".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            errors(&partner),
+            vec![
+                error("name", FieldErrorKind::Required),
+                error("memo", FieldErrorKind::TooLong(MAX_TEXT_LENGTH)),
+                error("tel", FieldErrorKind::InvalidFormat),
+                error("email", FieldErrorKind::InvalidFormat),
+                error("cc_emails", FieldErrorKind::InvalidFormat),
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_update_partner_and_its_departments() {
+        assert!(errors(&UpdatePartner::default()).is_empty());
+
+        let partner = UpdatePartner {
+            name: Patch::Clear,
+            departments: vec![
+                UpdateDepartmentInfo {
+                    email: Patch::Set("info@example.com".into()),
+                    ..Default::default()
+                },
+                UpdateDepartmentInfo {
+                    zip: Patch::Set("123".into()),
+                    cc_emails: Patch::Set("a".into()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            errors(&partner),
+            vec![
+                error("name", FieldErrorKind::Required),
+                error("departments[1].zip", FieldErrorKind::InvalidFormat),
+                error("departments[1].cc_emails", FieldErrorKind::InvalidFormat),
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_new_billing() {
+        let billing = NewBilling {
+            department_id: DepartmentId::new("DEPARTMENT"),
+            billing_date: Some(date(2017, 9, 30)),
+            due_date: Some(date(2017, 9, 30)),
+            items: vec![
+                NewBillingItem {
+                    quantity: Some("1.5".into()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert!(errors(&billing).is_empty());
+
+        let billing = NewBilling {
+            billing_date: Some(date(2017, 9, 30)),
+            due_date: Some(date(2017, 9, 29)),
+            items: vec![
+                NewBillingItem::default(),
+                NewBillingItem {
+                    quantity: Some("たくさん".into()),
+                    unit: Some(long(MAX_LENGTH + 1)),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            errors(&billing),
+            vec![
+                error("department_id", FieldErrorKind::Required),
+                error("due_date", FieldErrorKind::Before("billing_date".into())),
+                error("items[1].quantity", FieldErrorKind::InvalidFormat),
+                error("items[1].unit", FieldErrorKind::TooLong(MAX_LENGTH)),
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_update_billing() {
+        assert!(errors(&UpdateBilling::default()).is_empty());
+
+        let billing = UpdateBilling {
+            department_id: Patch::Clear,
+            billing_number: Patch::Set(String::new()),
+            billing_date: Patch::Set(date(2017, 10, 1)),
+            due_date: Patch::Set(date(2017, 9, 30)),
+            items: vec![
+                UpdateBillingItem {
+                    id: Some(BillingItemId::new("REMOVED")),
+                    quantity: Patch::Set("不正".into()),
+                    _destroy: true,
+                    ..Default::default()
+                },
+                UpdateBillingItem {
+                    quantity: Patch::Set("不正".into()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            errors(&billing),
+            vec![
+                error("department_id", FieldErrorKind::Required),
+                error("billing_number", FieldErrorKind::Required),
+                error("due_date", FieldErrorKind::Before("billing_date".into())),
+                error("items[1].quantity", FieldErrorKind::InvalidFormat),
+            ]
+        );
+
+        // 片方の日付だけなら比べない
+        let billing = UpdateBilling {
+            due_date: Patch::Set(date(2000, 1, 1)),
+            ..Default::default()
+        };
+        assert!(errors(&billing).is_empty());
+    }
+
+    #[test]
+    fn validates_new_item() {
+        let item = NewItem {
+            name: "品目".into(),
+            ..Default::default()
+        };
+        assert!(errors(&item).is_empty());
+
+        let item = NewItem {
+            detail: Some(long(MAX_TEXT_LENGTH + 1)),
+            ..Default::default()
+        };
+        assert_eq!(
+            errors(&item),
+            vec![
+                error("name", FieldErrorKind::Required),
+                error("detail", FieldErrorKind::TooLong(MAX_TEXT_LENGTH)),
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_update_item() {
+        assert!(errors(&UpdateItem::default()).is_empty());
+
+        let item = UpdateItem {
+            name: Patch::Clear,
+            code: Patch::Set(long(MAX_LENGTH + 1)),
+            unit: Patch::Clear,
+            ..Default::default()
+        };
+        assert_eq!(
+            errors(&item),
+            vec![
+                error("name", FieldErrorKind::Required),
+                error("code", FieldErrorKind::TooLong(MAX_LENGTH)),
+            ]
+        );
+    }
+
+    #[test]
+    fn displays_field_errors() {
+        assert_eq!(
+            error("items[0].quantity", FieldErrorKind::InvalidFormat).to_string(),
+            "items[0].quantity has an invalid format"
+        );
+        assert_eq!(
+            error("due_date", FieldErrorKind::Before("billing_date".into())).to_string(),
+            "due_date must not be before billing_date"
+        );
+    }
+}